@@ -0,0 +1,186 @@
+use chrono::{DateTime, FixedOffset, TimeDelta, Utc};
+
+use crate::enums;
+use crate::responses;
+
+/// OHLCV candle with mid prices (average of bid and ask).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candle {
+    /// Start of the candle in UTC (snapshot_time_UTC of the prices)
+    pub time: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+impl Candle {
+    /// Convert the prices of the API into a mid price candle.
+    /// Returns None if snapshot_time_UTC can not be parsed.
+    pub fn from_prices(prices: &responses::Prices) -> Option<Self> {
+        let time = prices.snapshot_time_utc()?;
+
+        Some(Self {
+            time,
+            open: Self::mid(&prices.open_price),
+            high: Self::mid(&prices.high_price),
+            low: Self::mid(&prices.low_price),
+            close: Self::mid(&prices.close_price),
+            volume: prices.last_traded_volume,
+        })
+    }
+
+    /// Start of the candle in the local time of the broker, e.g. with CapitalDotComAPI::session_timezone
+    pub fn local_time(&self, timezone: FixedOffset) -> DateTime<FixedOffset> {
+        self.time.with_timezone(&timezone)
+    }
+
+    fn mid(price: &responses::Price) -> f64 {
+        (price.bid + price.ask) / 2.
+    }
+}
+
+/// Start of the bucket of the resolution the time is in.
+/// Buckets are aligned to UTC, weeks start on monday.
+fn bucket_start(time: DateTime<Utc>, resolution: enums::Resolution) -> DateTime<Utc> {
+    // 1970-01-01 was a thursday, shift weeks so they start on monday.
+    let offset = match resolution {
+        enums::Resolution::WEEK => TimeDelta::days(3),
+        _ => TimeDelta::zero(),
+    };
+
+    let length = resolution.duration().num_seconds();
+    let seconds = (time + offset).timestamp();
+    let start = seconds - seconds.rem_euclid(length);
+
+    DateTime::from_timestamp(start, 0).unwrap_or(time) - offset
+}
+
+/// Aggregate candles into coarser candles of the target resolution.
+///  * Buckets are aligned to the resolution (e.g. 4 hour candles start at 00:00, 04:00, ...)
+///  * Buckets without any candle (gaps) are left out
+///  * The last bucket may be incomplete if the data ends before the bucket does
+pub fn resample(candles: &[Candle], target: enums::Resolution) -> Vec<Candle> {
+    let mut sorted: Vec<&Candle> = candles.iter().collect();
+    sorted.sort_by_key(|candle| candle.time);
+
+    let mut resampled: Vec<Candle> = Vec::new();
+    for candle in sorted {
+        let start = bucket_start(candle.time, target);
+
+        match resampled.last_mut() {
+            Some(last) if last.time == start => {
+                last.high = last.high.max(candle.high);
+                last.low = last.low.min(candle.low);
+                last.close = candle.close;
+                last.volume += candle.volume;
+            }
+            _ => resampled.push(Candle {
+                time: start,
+                ..candle.clone()
+            }),
+        }
+    }
+
+    resampled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(time: &str, open: f64, high: f64, low: f64, close: f64) -> Candle {
+        Candle {
+            time: DateTime::parse_from_rfc3339(time).unwrap().to_utc(),
+            open,
+            high,
+            low,
+            close,
+            volume: 1.,
+        }
+    }
+
+    #[test]
+    fn from_prices() {
+        let prices: responses::Prices = serde_json::from_str(
+            r#"{
+                "snapshotTime": "2024-06-11T15:00:00",
+                "snapshotTimeUTC": "2024-06-11T13:00:00",
+                "openPrice": {"bid": 1.0, "ask": 2.0},
+                "closePrice": {"bid": 2.0, "ask": 3.0},
+                "highPrice": {"bid": 4.0, "ask": 5.0},
+                "lowPrice": {"bid": 0.0, "ask": 1.0},
+                "lastTradedVolume": 42
+            }"#,
+        )
+        .unwrap();
+
+        let timezone = FixedOffset::east_opt(2 * 3600).unwrap();
+        assert_eq!(
+            prices.snapshot_time_local(timezone).unwrap(),
+            prices.snapshot_time_utc().unwrap()
+        );
+
+        let candle = Candle::from_prices(&prices).unwrap();
+        assert_eq!(
+            candle.time,
+            DateTime::from_timestamp(1718110800, 0).unwrap()
+        );
+        assert_eq!(
+            candle.local_time(timezone).to_rfc3339(),
+            "2024-06-11T15:00:00+02:00"
+        );
+        assert_eq!(candle.open, 1.5);
+        assert_eq!(candle.close, 2.5);
+        assert_eq!(candle.high, 4.5);
+        assert_eq!(candle.low, 0.5);
+        assert_eq!(candle.volume, 42.);
+    }
+
+    #[test]
+    fn resample_hours() {
+        let candles = vec![
+            candle("2024-06-11T01:00:00Z", 1., 2., 0.5, 1.5),
+            candle("2024-06-11T00:00:00Z", 0.5, 1., 0.2, 1.),
+            candle("2024-06-11T03:00:00Z", 1.5, 5., 1., 4.),
+            // gap, no candles between 04:00 and 08:00
+            candle("2024-06-11T09:00:00Z", 4., 4.5, 3., 3.5),
+        ];
+
+        let resampled = resample(&candles, enums::Resolution::Hour4);
+        assert_eq!(resampled.len(), 2);
+
+        let mut expected = candle("2024-06-11T00:00:00Z", 0.5, 5., 0.2, 4.);
+        expected.volume = 3.;
+        assert_eq!(resampled[0], expected);
+
+        // Incomplete trailing bucket
+        assert_eq!(
+            resampled[1].time,
+            candle("2024-06-11T08:00:00Z", 0., 0., 0., 0.).time
+        );
+        assert_eq!(resampled[1].close, 3.5);
+        assert_eq!(resampled[1].volume, 1.);
+    }
+
+    #[test]
+    fn resample_weeks_start_on_monday() {
+        // 2024-06-12 is a wednesday, 2024-06-17 a monday
+        let candles = vec![
+            candle("2024-06-12T00:00:00Z", 1., 1., 1., 1.),
+            candle("2024-06-16T23:00:00Z", 2., 2., 2., 2.),
+            candle("2024-06-17T00:00:00Z", 3., 3., 3., 3.),
+        ];
+
+        let resampled = resample(&candles, enums::Resolution::WEEK);
+        assert_eq!(resampled.len(), 2);
+        assert_eq!(
+            resampled[0].time,
+            DateTime::parse_from_rfc3339("2024-06-10T00:00:00Z").unwrap()
+        );
+        assert_eq!(
+            resampled[1].time,
+            DateTime::parse_from_rfc3339("2024-06-17T00:00:00Z").unwrap()
+        );
+    }
+}
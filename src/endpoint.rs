@@ -1,12 +1,18 @@
 use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
 
 use reqwest::header::HeaderMap;
 use reqwest::header::HeaderValue;
+use serde::{Deserialize, Serialize};
 
 use crate::enums;
 use crate::request_bodies;
 use crate::responses;
-use crate::traits::{self, ReqwestUtils};
+use crate::traits::{self, CapitalDotComEndpoints, ReqwestUtils};
 use crate::CapitalDotComError;
 
 #[derive(Debug)]
@@ -20,6 +26,8 @@ pub struct CapitalDotComApiEndpoints {
     password: String,
     encryption_key: String, // TODO: Implement encryption.
     auth_header_map: HeaderMap,
+    retry_policy: RetryPolicy,
+    rate_limit_status: Option<RateLimitStatus>,
 
     http_client: reqwest::Client,
 }
@@ -29,6 +37,8 @@ impl CapitalDotComApiEndpoints {
         x_cap_api_key: String,
         identifier: String,
         password: String,
+        retry_policy: RetryPolicy,
+        http_client: reqwest::Client,
     ) -> Self {
         Self {
             base_url: Self::get_session_url_from_sessiontype(session_type),
@@ -39,7 +49,9 @@ impl CapitalDotComApiEndpoints {
             password,
             encryption_key: String::new(),
             auth_header_map: HeaderMap::new(),
-            http_client: reqwest::Client::new(),
+            retry_policy,
+            rate_limit_status: None,
+            http_client,
         }
     }
 
@@ -58,6 +70,63 @@ impl CapitalDotComApiEndpoints {
         endpoint
     }
 
+    /// Send the requests to another server (e.g. a mock server for tests)
+    pub fn set_base_url(&mut self, base_url: String) {
+        self.base_url = base_url;
+    }
+
+    pub fn cst(&self) -> &str {
+        &self.cst
+    }
+
+    pub fn x_security_token(&self) -> &str {
+        &self.x_security_token
+    }
+
+    pub fn rate_limit_status(&self) -> Option<RateLimitStatus> {
+        self.rate_limit_status
+    }
+
+    /// Request to a path of the API, without authorization headers.
+    pub fn request_builder(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        self.http_client.request(method, self.get_url(path))
+    }
+
+    /// Send the request with the authorization headers and return the response as is.
+    /// The status code is not checked and the body is not read.
+    pub async fn send_authenticated(
+        &mut self,
+        request_builder: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, CapitalDotComError> {
+        self.has_credentials()?;
+
+        let response = match request_builder
+            .headers(self.auth_header_map.clone())
+            .send()
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => return Err(CapitalDotComError::ReqwestError(e)),
+        };
+
+        if let Some(rate_limit_status) = RateLimitStatus::from_headers(
+            response.headers(),
+            response.status().as_u16(),
+            Utc::now(),
+        ) {
+            self.rate_limit_status = Some(rate_limit_status);
+        }
+
+        Ok(response)
+    }
+
+    /// Whether the response headers contain both session tokens
+    fn has_auth_tokens(headers: &HashMap<String, String>) -> bool {
+        ["cst", "x-security-token"]
+            .iter()
+            .all(|token| headers.get(*token).is_some_and(|value| !value.is_empty()))
+    }
+
     fn update_auth(&mut self, headers: HashMap<String, String>) {
         self.x_security_token = match headers.get("x-security-token") {
             Some(x_security_token) => x_security_token.to_owned(),
@@ -89,7 +158,8 @@ impl traits::CapitalDotComEndpoints for CapitalDotComApiEndpoints {
     ) -> Result<(HashMap<String, String>, responses::ServerTimeResponse), CapitalDotComError> {
         let request_builder = self.http_client.get(Self::get_url(&self, "/api/v1/time"));
 
-        Self::request_data(request_builder).await
+        self.request_data(request_builder, self.retry_policy.read)
+            .await
     }
 
     async fn ping(
@@ -102,7 +172,8 @@ impl traits::CapitalDotComEndpoints for CapitalDotComApiEndpoints {
             .get(Self::get_url(&self, "/api/v1/ping"))
             .headers(self.auth_header_map.clone());
 
-        Self::request_data(request_builder).await
+        self.request_data(request_builder, self.retry_policy.read)
+            .await
     }
 
     async fn get_encryption_key(
@@ -114,7 +185,8 @@ impl traits::CapitalDotComEndpoints for CapitalDotComApiEndpoints {
             .get(Self::get_url(&self, "/api/v1/session/encryptionKey"))
             .header("X-CAP-API-KEY", &self.x_cap_api_key);
 
-        Self::request_data(request_builder).await
+        self.request_data(request_builder, self.retry_policy.session)
+            .await
     }
 
     async fn get_session_details(
@@ -128,7 +200,8 @@ impl traits::CapitalDotComEndpoints for CapitalDotComApiEndpoints {
             .get(Self::get_url(&self, "/api/v1/session"))
             .headers(self.auth_header_map.clone());
 
-        Self::request_data(request_builder).await
+        self.request_data(request_builder, self.retry_policy.read)
+            .await
     }
 
     async fn create_new_session(
@@ -147,7 +220,14 @@ impl traits::CapitalDotComEndpoints for CapitalDotComApiEndpoints {
             .header("Content-Type", "application/json")
             .body(body);
 
-        let (headers, body) = Self::request_data(request_builder).await?;
+        let (headers, body) = self
+            .request_data(request_builder, self.retry_policy.session)
+            .await?;
+
+        // Both tokens are needed for every following request
+        if !Self::has_auth_tokens(&headers) {
+            return Err(CapitalDotComError::LoginFailed);
+        }
 
         // Update authorization values
         self.update_auth(headers.clone());
@@ -165,7 +245,8 @@ impl traits::CapitalDotComEndpoints for CapitalDotComApiEndpoints {
             .get(Self::get_url(&self, "/api/v1/accounts"))
             .headers(self.auth_header_map.clone());
 
-        Self::request_data(request_builder).await
+        self.request_data(request_builder, self.retry_policy.read)
+            .await
     }
 
     async fn switch_active_account(
@@ -186,7 +267,36 @@ impl traits::CapitalDotComEndpoints for CapitalDotComApiEndpoints {
             .header("Content-Type", "application/json")
             .body(body);
 
-        Self::request_data(request_builder).await
+        let (headers, body) = self
+            .request_data(request_builder, self.retry_policy.session)
+            .await?;
+
+        // The server may rotate the tokens on an account switch, keep the old ones if it does not
+        if Self::has_auth_tokens(&headers) {
+            self.update_auth(headers.clone());
+        }
+
+        Ok((headers, body))
+    }
+
+    async fn get_account_preferences(
+        &mut self,
+    ) -> Result<
+        (
+            HashMap<String, String>,
+            responses::AccountPreferencesResponse,
+        ),
+        CapitalDotComError,
+    > {
+        self.has_credentials()?;
+
+        let request_builder = self
+            .http_client
+            .get(Self::get_url(&self, "/api/v1/accounts/preferences"))
+            .headers(self.auth_header_map.clone());
+
+        self.request_data(request_builder, self.retry_policy.read)
+            .await
     }
 
     async fn session_log_out(
@@ -200,7 +310,8 @@ impl traits::CapitalDotComEndpoints for CapitalDotComApiEndpoints {
             .delete(Self::get_url(&self, "/api/v1/session"))
             .headers(self.auth_header_map.clone());
 
-        Self::request_data(request_builder).await
+        self.request_data(request_builder, self.retry_policy.session)
+            .await
     }
 
     async fn order_confirmation(
@@ -223,7 +334,8 @@ impl traits::CapitalDotComEndpoints for CapitalDotComApiEndpoints {
             ))
             .headers(self.auth_header_map.clone());
 
-        Self::request_data(request_builder).await
+        self.request_data(request_builder, self.retry_policy.read)
+            .await
     }
 
     async fn get_all_positions(
@@ -237,7 +349,8 @@ impl traits::CapitalDotComEndpoints for CapitalDotComApiEndpoints {
             .get(Self::get_url(&self, "/api/v1/positions"))
             .headers(self.auth_header_map.clone());
 
-        Self::request_data(request_builder).await
+        self.request_data(request_builder, self.retry_policy.read)
+            .await
     }
 
     async fn open_position(
@@ -256,7 +369,28 @@ impl traits::CapitalDotComEndpoints for CapitalDotComApiEndpoints {
             .header("Content-Type", "application/json")
             .body(body);
 
-        Self::request_data(request_builder).await
+        self.request_data(request_builder, self.retry_policy.order)
+            .await
+    }
+
+    async fn create_working_order(
+        &mut self,
+        working_order_data: request_bodies::CreateWorkingOrderBody,
+    ) -> Result<(HashMap<String, String>, responses::DealReferenceResponse), CapitalDotComError>
+    {
+        self.has_credentials()?;
+
+        let body = Self::get_json_from_value(working_order_data)?;
+
+        let request_builder = self
+            .http_client
+            .post(Self::get_url(&self, "/api/v1/workingorders"))
+            .headers(self.auth_header_map.clone())
+            .header("Content-Type", "application/json")
+            .body(body);
+
+        self.request_data(request_builder, self.retry_policy.order)
+            .await
     }
 
     async fn get_position(
@@ -273,7 +407,8 @@ impl traits::CapitalDotComEndpoints for CapitalDotComApiEndpoints {
             ))
             .headers(self.auth_header_map.clone());
 
-        Self::request_data(request_builder).await
+        self.request_data(request_builder, self.retry_policy.read)
+            .await
     }
 
     async fn update_position(
@@ -296,7 +431,8 @@ impl traits::CapitalDotComEndpoints for CapitalDotComApiEndpoints {
             .header("Content-Type", "application/json")
             .body(body);
 
-        Self::request_data(request_builder).await
+        self.request_data(request_builder, self.retry_policy.order)
+            .await
     }
 
     async fn close_position(
@@ -314,13 +450,125 @@ impl traits::CapitalDotComEndpoints for CapitalDotComApiEndpoints {
             ))
             .headers(self.auth_header_map.clone());
 
-        Self::request_data(request_builder).await
+        self.request_data(request_builder, self.retry_policy.order)
+            .await
+    }
+
+    async fn get_all_working_orders(
+        &mut self,
+    ) -> Result<(HashMap<String, String>, responses::AllWorkingOrdersResponse), CapitalDotComError>
+    {
+        self.has_credentials()?;
+
+        let request_builder = self
+            .http_client
+            .get(Self::get_url(&self, "/api/v1/workingorders"))
+            .headers(self.auth_header_map.clone());
+
+        self.request_data(request_builder, self.retry_policy.read)
+            .await
     }
 
-    /// Search market from search term.
+    async fn delete_working_order(
+        &mut self,
+        deal_id: &str,
+    ) -> Result<(HashMap<String, String>, responses::DealReferenceResponse), CapitalDotComError>
+    {
+        self.has_credentials()?;
+
+        let request_builder = self
+            .http_client
+            .delete(Self::get_url(
+                &self,
+                &format!("/api/v1/workingorders/{}", deal_id),
+            ))
+            .headers(self.auth_header_map.clone());
+
+        self.request_data(request_builder, self.retry_policy.order)
+            .await
+    }
+
+    async fn get_activity_history(
+        &mut self,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+    ) -> Result<(HashMap<String, String>, responses::ActivityHistoryResponse), CapitalDotComError>
+    {
+        self.has_credentials()?;
+
+        let request_builder = self
+            .http_client
+            .get(Self::get_url(&self, "/api/v1/history/activity"))
+            .query(&[
+                ("from", Self::get_readable_from_datetime(from)),
+                ("to", Self::get_readable_from_datetime(to)),
+                ("detailed", String::from("true")),
+            ])
+            .headers(self.auth_header_map.clone());
+
+        self.request_data(request_builder, self.retry_policy.read)
+            .await
+    }
+
+    async fn get_transaction_history(
+        &mut self,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+        transaction_type: Option<enums::TransactionType>,
+    ) -> Result<
+        (
+            HashMap<String, String>,
+            responses::TransactionHistoryResponse,
+        ),
+        CapitalDotComError,
+    > {
+        self.has_credentials()?;
+
+        let mut request_builder = self
+            .http_client
+            .get(Self::get_url(&self, "/api/v1/history/transactions"))
+            .query(&[
+                ("from", Self::get_readable_from_datetime(from)),
+                ("to", Self::get_readable_from_datetime(to)),
+            ])
+            .headers(self.auth_header_map.clone());
+        if let Some(transaction_type) = transaction_type {
+            request_builder = request_builder.query(&[("type", transaction_type)]);
+        }
+
+        self.request_data(request_builder, self.retry_policy.read)
+            .await
+    }
+
+    async fn get_market_navigation(
+        &mut self,
+        node_id: Option<&str>,
+    ) -> Result<(HashMap<String, String>, responses::MarketNavigationResponse), CapitalDotComError>
+    {
+        self.has_credentials()?;
+
+        let request_builder = match node_id {
+            Some(node_id) => self
+                .http_client
+                .get(Self::get_url(
+                    &self,
+                    &format!("/api/v1/marketnavigation/{}", node_id),
+                ))
+                .query(&[("limit", "500")]),
+            None => self
+                .http_client
+                .get(Self::get_url(&self, "/api/v1/marketnavigation")),
+        }
+        .headers(self.auth_header_map.clone());
+
+        self.request_data(request_builder, self.retry_policy.read)
+            .await
+    }
+
+    /// Search market from search term and/or epics.
     async fn get_market_details(
         &mut self,
-        search_term: &str,
+        search_term: Option<&str>,
         epics: Vec<String>,
     ) -> Result<(HashMap<String, String>, responses::MarketDetailsResponse), CapitalDotComError>
     {
@@ -330,28 +578,21 @@ impl traits::CapitalDotComEndpoints for CapitalDotComApiEndpoints {
             return Err(CapitalDotComError::TooManyParameters);
         }
 
-        let mut epic_query = String::new();
-        for (i, epic) in epics.iter().enumerate() {
-            epic_query.push_str(&epic);
-
-            if i <= epics.len() - 1 {
-                epic_query.push(',');
-            }
-        }
-
         let mut request_builder = self
             .http_client
             .get(Self::get_url(&self, "/api/v1/markets"))
             .headers(self.auth_header_map.clone());
 
-        if epic_query.is_empty() {
+        if let Some(search_term) = search_term {
             request_builder = request_builder.query(&[("searchTerm", search_term)]);
-        } else {
-            request_builder =
-                request_builder.query(&[("searchTerm", search_term), ("epics", &epic_query)]);
         }
 
-        Self::request_data(request_builder).await
+        if !epics.is_empty() {
+            request_builder = request_builder.query(&[("epics", epics.join(","))]);
+        }
+
+        self.request_data(request_builder, self.retry_policy.read)
+            .await
     }
 
     async fn get_single_market_details(
@@ -371,7 +612,8 @@ impl traits::CapitalDotComEndpoints for CapitalDotComApiEndpoints {
             .get(Self::get_url(&self, &format!("/api/v1/markets/{}", epic)))
             .headers(self.auth_header_map.clone());
 
-        Self::request_data(request_builder).await
+        self.request_data(request_builder, self.retry_policy.read)
+            .await
     }
 
     async fn get_historical_prices(
@@ -400,22 +642,283 @@ impl traits::CapitalDotComEndpoints for CapitalDotComApiEndpoints {
             None => request_builder,
         };
 
-        Self::request_data(request_builder).await
+        self.request_data(request_builder, self.retry_policy.read)
+            .await
+    }
+
+    async fn request_raw(
+        &mut self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<serde_json::Value>,
+    ) -> Result<(HashMap<String, String>, serde_json::Value), CapitalDotComError> {
+        self.has_credentials()?;
+
+        let retry = match method {
+            reqwest::Method::GET => self.retry_policy.read,
+            _ => self.retry_policy.order,
+        };
+
+        let mut request_builder = self
+            .http_client
+            .request(method, Self::get_url(&self, path))
+            .headers(self.auth_header_map.clone());
+
+        if let Some(body) = body {
+            request_builder = request_builder
+                .header("Content-Type", "application/json")
+                .body(Self::get_json_from_value(body)?);
+        }
+
+        self.request_data(request_builder, retry).await
+    }
+
+    async fn request_typed<T: for<'a> Deserialize<'a>, B: Serialize>(
+        &mut self,
+        method: reqwest::Method,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Option<B>,
+    ) -> Result<(HashMap<String, String>, T), CapitalDotComError> {
+        self.has_credentials()?;
+
+        let retry = match method {
+            reqwest::Method::GET => self.retry_policy.read,
+            _ => self.retry_policy.order,
+        };
+
+        let mut request_builder = self
+            .http_client
+            .request(method, Self::get_url(&self, path))
+            .query(query)
+            .headers(self.auth_header_map.clone());
+
+        if let Some(body) = body {
+            request_builder = request_builder
+                .header("Content-Type", "application/json")
+                .body(Self::get_json_from_value(body)?);
+        }
+
+        self.request_data(request_builder, retry).await
     }
 
     fn has_credentials(&self) -> Result<(), CapitalDotComError> {
-        if !self.x_security_token.is_empty() || !self.cst.is_empty() {
+        if !self.x_security_token.is_empty() && !self.cst.is_empty() {
             Ok(())
         } else {
             Err(CapitalDotComError::MissingAuthorization)
         }
     }
+
+    fn set_rate_limit_status(&mut self, rate_limit_status: RateLimitStatus) {
+        self.rate_limit_status = Some(rate_limit_status);
+    }
 }
 
 impl ReqwestUtils for CapitalDotComApiEndpoints {}
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
 pub enum SessionType {
     Live,
     Demo,
 }
+impl Display for SessionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let string = match self {
+            Self::Live => "live",
+            Self::Demo => "demo",
+        };
+
+        write!(f, "{}", string)
+    }
+}
+impl FromStr for SessionType {
+    type Err = CapitalDotComError;
+
+    /// Case insensitive, accepts "live" and "demo".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "live" => Ok(Self::Live),
+            "demo" => Ok(Self::Demo),
+            _ => Err(CapitalDotComError::InvalidSessionType(s.to_string())),
+        }
+    }
+}
+
+/// How often a failed request gets retried and how long to wait between the attempts.
+/// Only transient errors (connection errors, 429 and 5xx) are retried.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Retry {
+    pub retries: u32,
+    pub delay: Duration,
+}
+impl Retry {
+    pub fn new(retries: u32, delay: Duration) -> Self {
+        Self { retries, delay }
+    }
+
+    pub fn none() -> Self {
+        Self::new(0, Duration::ZERO)
+    }
+}
+
+/// Retry settings per request category.
+///  * read: requests that only read data. Default = 3 retries, 500 ms apart
+///  * order: requests that open, update or close positions and orders. Retrying these can open duplicates. Default = no retries
+///  * session: session creation, switching and log out. Max of 1 session request per second. Default = 1 retry, 1 s apart
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    pub read: Retry,
+    pub order: Retry,
+    pub session: Retry,
+}
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            read: Retry::new(3, Duration::from_millis(500)),
+            order: Retry::none(),
+            session: Retry::new(1, Duration::from_secs(1)),
+        }
+    }
+}
+
+/// What to do when the server reported that no requests are left.
+///  * Wait: sleep until the window resets, then continue. Default
+///  * Fail: return CapitalDotComError::RequestingTooFast with the time until the reset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum RateLimitBehavior {
+    #[default]
+    Wait,
+    Fail,
+}
+
+/// Rate limit state reported by the server in the headers of the last response.
+///  * remaining: requests left in the current window
+///  * reset_at: when the window resets (or when to retry after a 429)
+///  * too_many_requests: the response was a 429
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitStatus {
+    pub remaining: Option<u32>,
+    pub reset_at: Option<DateTime<Utc>>,
+    pub too_many_requests: bool,
+}
+impl RateLimitStatus {
+    /// Values above this are unix timestamps, below seconds from now
+    const MIN_RESET_TIMESTAMP: i64 = 1_000_000_000;
+
+    /// Read the x-ratelimit-*, ratelimit-* and retry-after headers of a response with the status code.
+    /// None if the response has none of them.
+    pub(crate) fn from_headers(
+        headers: &HeaderMap,
+        status_code: u16,
+        now: DateTime<Utc>,
+    ) -> Option<Self> {
+        let header = |names: &[&str]| {
+            names.iter().find_map(|name| {
+                headers
+                    .get(*name)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.trim().parse::<i64>().ok())
+            })
+        };
+
+        let remaining = header(&["x-ratelimit-remaining", "ratelimit-remaining"])
+            .and_then(|remaining| u32::try_from(remaining).ok());
+        let reset_at =
+            header(&["x-ratelimit-reset", "ratelimit-reset", "retry-after"]).and_then(|reset| {
+                match reset {
+                    reset if reset >= Self::MIN_RESET_TIMESTAMP => {
+                        DateTime::from_timestamp(reset, 0)
+                    }
+                    reset => Some(now + TimeDelta::seconds(reset)),
+                }
+            });
+
+        if remaining.is_none() && reset_at.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            remaining,
+            reset_at,
+            too_many_requests: status_code == 429,
+        })
+    }
+
+    /// Time until the server accepts requests again.
+    /// None if there are requests left and the last response was not a 429.
+    pub fn wait_time(&self, now: DateTime<Utc>) -> Option<Duration> {
+        if self.remaining != Some(0) && !self.too_many_requests {
+            return None;
+        }
+
+        (self.reset_at? - now).to_std().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_type_from_str() {
+        assert_eq!("live".parse::<SessionType>().unwrap(), SessionType::Live);
+        assert_eq!(" Demo ".parse::<SessionType>().unwrap(), SessionType::Demo);
+        assert!(matches!(
+            "paper".parse::<SessionType>(),
+            Err(CapitalDotComError::InvalidSessionType(_))
+        ));
+
+        assert_eq!(SessionType::Live.to_string(), "live");
+        assert_eq!(SessionType::Demo.to_string(), "demo");
+    }
+
+    #[test]
+    fn rate_limit_status_from_headers() {
+        let now = DateTime::from_timestamp(1718110800, 0).unwrap();
+        let headers = |values: &[(&'static str, &str)]| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in values {
+                headers.insert(*name, HeaderValue::from_str(value).unwrap());
+            }
+            headers
+        };
+
+        assert_eq!(RateLimitStatus::from_headers(&headers(&[]), 429, now), None);
+
+        let status = RateLimitStatus::from_headers(
+            &headers(&[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset", "2")]),
+            200,
+            now,
+        )
+        .unwrap();
+        assert_eq!(status.remaining, Some(0));
+        assert_eq!(status.reset_at, Some(now + TimeDelta::seconds(2)));
+        assert_eq!(status.wait_time(now), Some(Duration::from_secs(2)));
+
+        let status = RateLimitStatus::from_headers(
+            &headers(&[
+                ("ratelimit-remaining", "7"),
+                ("ratelimit-reset", "1718110860"),
+            ]),
+            200,
+            now,
+        )
+        .unwrap();
+        assert_eq!(status.remaining, Some(7));
+        assert_eq!(status.reset_at, DateTime::from_timestamp(1718110860, 0));
+        assert_eq!(status.wait_time(now), None);
+
+        // A 429 usually only has retry-after
+        let status =
+            RateLimitStatus::from_headers(&headers(&[("retry-after", "3")]), 429, now).unwrap();
+        assert_eq!(status.remaining, None);
+        assert!(status.too_many_requests);
+        assert_eq!(status.wait_time(now), Some(Duration::from_secs(3)));
+        assert_eq!(status.wait_time(now + TimeDelta::seconds(4)), None);
+
+        let status =
+            RateLimitStatus::from_headers(&headers(&[("retry-after", "3")]), 200, now).unwrap();
+        assert_eq!(status.wait_time(now), None);
+    }
+}
@@ -1,19 +1,35 @@
 use std::fmt::Display;
 
-use serde::{Deserialize, Serialize};
+use chrono::{FixedOffset, NaiveDate};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum InstrumentType {
     COMMODITIES,
     SHARES,
+    CURRENCIES,
+    CRYPTOCURRENCIES,
+    INDICES,
+}
+impl InstrumentType {
+    /// Name of the group in the leverages of the account preferences.
+    pub fn leverage_group(&self) -> &'static str {
+        match self {
+            InstrumentType::COMMODITIES => "COMMODITIES",
+            InstrumentType::SHARES => "SHARES",
+            InstrumentType::CURRENCIES => "CURRENCIES",
+            InstrumentType::CRYPTOCURRENCIES => "CRYPTOCURRENCIES",
+            InstrumentType::INDICES => "INDICES",
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum AccountType {
     CFD,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Currency {
     EUR,
     EURd,
@@ -38,16 +54,16 @@ pub enum AccountStatus {
     DISABLED,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Direction {
     BUY,
     SELL,
 }
-impl Clone for Direction {
-    fn clone(&self) -> Self {
+impl Direction {
+    pub fn opposite(&self) -> Self {
         match self {
-            Self::BUY => Self::BUY,
-            Self::SELL => Self::SELL,
+            Self::BUY => Self::SELL,
+            Self::SELL => Self::BUY,
         }
     }
 }
@@ -62,18 +78,171 @@ impl Display for Direction {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum OrderType {
+    LIMIT,
+    STOP,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TimeInForce {
+    GoodTillCancelled,
+    GoodTillDate,
+}
+
+/// Expiry of an instrument
+///  * Dfb: daily funded bet ("DFB")
+///  * None: the instrument does not expire ("-")
+///  * Date: expiry date (e.g. "2024-12-20")
+///  * Other: a value that could not be parsed
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expiry {
+    Dfb,
+    None,
+    Date(NaiveDate),
+    Other(String),
+}
+impl Expiry {
+    const DATE_FORMATS: [&'static str; 2] = ["%Y-%m-%d", "%d-%m-%Y"];
+
+    fn parse(expiry: &str) -> Self {
+        match expiry.trim() {
+            "DFB" => Self::Dfb,
+            "-" | "" => Self::None,
+            expiry => Self::DATE_FORMATS
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(expiry, format).ok())
+                .map_or_else(|| Self::Other(expiry.to_string()), Self::Date),
+        }
+    }
+}
+impl Display for Expiry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Dfb => write!(f, "DFB"),
+            Self::None => write!(f, "-"),
+            Self::Date(date) => write!(f, "{}", date.format("%Y-%m-%d")),
+            Self::Other(expiry) => write!(f, "{}", expiry),
+        }
+    }
+}
+impl Serialize for Expiry {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+impl<'de> Deserialize<'de> for Expiry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let expiry = String::deserialize(deserializer)?;
+
+        Ok(Self::parse(&expiry))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ActivityType {
+    Position,
+    WorkingOrder,
+    EditStopAndLimit,
+    System,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TransactionType {
+    Trade,
+    Swap,
+    Deposit,
+    Withdrawal,
+    TradeCommission,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Zone of the opening hours.
+///  * UTC: "UTC", "GMT" or "Etc/UTC"
+///  * Offset: a fixed offset like "+02:00" or "UTC-5"
+///  * Other: a named zone (e.g. "America/New_York"), no conversion possible without a time zone database
+#[derive(Debug, Clone, PartialEq)]
 pub enum TimeZone {
     UTC,
+    Offset(FixedOffset),
+    Other(String),
 }
+impl TimeZone {
+    fn parse(zone: &str) -> Self {
+        let zone = zone.trim();
+        let offset = zone
+            .strip_prefix("UTC")
+            .or_else(|| zone.strip_prefix("GMT"))
+            .unwrap_or(zone);
 
-#[derive(Debug, Serialize, Deserialize)]
+        match offset {
+            "" | "Etc/UTC" | "Z" => Self::UTC,
+            offset => Self::parse_offset(offset)
+                .map_or_else(|| Self::Other(zone.to_string()), Self::Offset),
+        }
+    }
+
+    /// "+02:00", "+0200", "+2" or "-5"
+    fn parse_offset(offset: &str) -> Option<FixedOffset> {
+        let (sign, offset) = match offset.split_at_checked(1)? {
+            ("+", offset) => (1, offset),
+            ("-", offset) => (-1, offset),
+            _ => return None,
+        };
+        let (hours, minutes) = match offset.split_once(':') {
+            Some((hours, minutes)) => (hours, minutes),
+            None if offset.len() == 4 => offset.split_at(2),
+            None => (offset, "0"),
+        };
+        let seconds = hours.parse::<i32>().ok()? * 3600 + minutes.parse::<i32>().ok()? * 60;
+
+        FixedOffset::east_opt(sign * seconds)
+    }
+
+    /// Offset to UTC, None for named zones.
+    pub fn offset(&self) -> Option<FixedOffset> {
+        match self {
+            Self::UTC => FixedOffset::east_opt(0),
+            Self::Offset(offset) => Some(*offset),
+            Self::Other(_) => None,
+        }
+    }
+}
+impl Display for TimeZone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UTC => write!(f, "UTC"),
+            Self::Offset(offset) => write!(f, "{}", offset),
+            Self::Other(zone) => write!(f, "{}", zone),
+        }
+    }
+}
+impl Serialize for TimeZone {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+impl<'de> Deserialize<'de> for TimeZone {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let zone = String::deserialize(deserializer)?;
+
+        Ok(Self::parse(&zone))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum Unit {
     PERCENTAGE,
     POINTS,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Resolution {
     MINUTE,
     Minute5,
@@ -84,6 +253,54 @@ pub enum Resolution {
     DAY,
     WEEK,
 }
+impl Resolution {
+    /// All resolutions the API supports, from the shortest to the longest.
+    pub fn all() -> &'static [Resolution] {
+        &[
+            Self::MINUTE,
+            Self::Minute5,
+            Self::Minute15,
+            Self::Minute30,
+            Self::HOUR,
+            Self::Hour4,
+            Self::DAY,
+            Self::WEEK,
+        ]
+    }
+
+    /// Short label for displaying, e.g. "5m" or "4h". The value for the API is to_string.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::MINUTE => "1m",
+            Self::Minute5 => "5m",
+            Self::Minute15 => "15m",
+            Self::Minute30 => "30m",
+            Self::HOUR => "1h",
+            Self::Hour4 => "4h",
+            Self::DAY => "1d",
+            Self::WEEK => "1w",
+        }
+    }
+
+    /// Length of one candle in seconds
+    pub fn seconds(&self) -> i64 {
+        self.duration().num_seconds()
+    }
+
+    /// Length of one candle
+    pub fn duration(&self) -> chrono::TimeDelta {
+        match self {
+            Self::MINUTE => chrono::TimeDelta::minutes(1),
+            Self::Minute5 => chrono::TimeDelta::minutes(5),
+            Self::Minute15 => chrono::TimeDelta::minutes(15),
+            Self::Minute30 => chrono::TimeDelta::minutes(30),
+            Self::HOUR => chrono::TimeDelta::hours(1),
+            Self::Hour4 => chrono::TimeDelta::hours(4),
+            Self::DAY => chrono::TimeDelta::days(1),
+            Self::WEEK => chrono::TimeDelta::weeks(1),
+        }
+    }
+}
 impl ToString for Resolution {
     fn to_string(&self) -> String {
         match self {
@@ -98,3 +315,88 @@ impl ToString for Resolution {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expiry() {
+        let parse = |json: &str| serde_json::from_str::<Expiry>(json).unwrap();
+
+        assert_eq!(parse(r#""DFB""#), Expiry::Dfb);
+        assert_eq!(parse(r#""-""#), Expiry::None);
+        assert_eq!(
+            parse(r#""2024-12-20""#),
+            Expiry::Date(NaiveDate::from_ymd_opt(2024, 12, 20).unwrap())
+        );
+        assert_eq!(
+            parse(r#""20-12-2024""#),
+            Expiry::Date(NaiveDate::from_ymd_opt(2024, 12, 20).unwrap())
+        );
+        assert_eq!(parse(r#""DEC-24""#), Expiry::Other(String::from("DEC-24")));
+
+        let date = Expiry::Date(NaiveDate::from_ymd_opt(2024, 12, 20).unwrap());
+        assert_eq!(serde_json::to_string(&date).unwrap(), r#""2024-12-20""#);
+    }
+
+    #[test]
+    fn time_zone() {
+        let parse = |json: &str| serde_json::from_str::<TimeZone>(json).unwrap();
+        let offset = |hours: i32, minutes: i32| {
+            TimeZone::Offset(FixedOffset::east_opt(hours * 3600 + minutes * 60).unwrap())
+        };
+
+        assert_eq!(parse(r#""UTC""#), TimeZone::UTC);
+        assert_eq!(parse(r#""Etc/UTC""#), TimeZone::UTC);
+        assert_eq!(parse(r#""+02:00""#), offset(2, 0));
+        assert_eq!(parse(r#""GMT-5""#), offset(-5, 0));
+        assert_eq!(parse(r#""UTC+0530""#), offset(5, 30));
+        assert_eq!(
+            parse(r#""America/New_York""#),
+            TimeZone::Other(String::from("America/New_York"))
+        );
+        assert!(parse(r#""America/New_York""#).offset().is_none());
+
+        assert_eq!(serde_json::to_string(&TimeZone::UTC).unwrap(), r#""UTC""#);
+        assert_eq!(serde_json::to_string(&offset(2, 0)).unwrap(), r#""+02:00""#);
+    }
+
+    #[test]
+    fn resolutions() {
+        let resolutions = Resolution::all();
+        assert_eq!(resolutions.len(), 8);
+        assert!(resolutions
+            .windows(2)
+            .all(|pair| pair[0].seconds() < pair[1].seconds()));
+
+        assert_eq!(Resolution::Minute5.label(), "5m");
+        assert_eq!(Resolution::Minute5.seconds(), 300);
+        assert_eq!(Resolution::Minute5.to_string(), "MINUTE_5");
+        assert_eq!(Resolution::WEEK.seconds(), 604800);
+    }
+
+    #[test]
+    fn copy_enums_round_trip() {
+        let direction = Direction::SELL;
+        let json = serde_json::to_string(&direction).unwrap();
+        assert_eq!(json, r#""SELL""#);
+        assert!(matches!(
+            serde_json::from_str::<Direction>(&json).unwrap(),
+            Direction::SELL
+        ));
+
+        let unit = Unit::PERCENTAGE;
+        let json = serde_json::to_string(&unit).unwrap();
+        assert_eq!(json, r#""PERCENTAGE""#);
+        assert!(matches!(
+            serde_json::from_str::<Unit>(&json).unwrap(),
+            Unit::PERCENTAGE
+        ));
+
+        assert_eq!(
+            serde_json::to_string(&AccountType::CFD).unwrap(),
+            r#""CFD""#
+        );
+    }
+}
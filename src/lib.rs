@@ -1,12 +1,20 @@
 use std::{
+    collections::{HashMap, HashSet},
     fmt::Display,
+    future::Future,
     string::FromUtf8Error,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
 };
 
 use reqwest::header::HeaderMap;
-use serde::Serialize;
+pub use reqwest::Method;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
+pub mod candle;
 pub mod responses;
 pub mod request_bodies;
 
@@ -14,8 +22,10 @@ mod endpoint;
 mod enums;
 mod traits;
 
-pub use endpoint::SessionType;
-pub use enums::{Direction, Resolution};
+pub use endpoint::{RateLimitBehavior, RateLimitStatus, Retry, RetryPolicy, SessionType};
+pub use enums::{
+    Currency, Direction, Expiry, InstrumentType, OrderType, Resolution, TransactionType,
+};
 pub use traits::CapitalDotComInterface;
 
 use endpoint::CapitalDotComApiEndpoints;
@@ -32,242 +42,1778 @@ use traits::CapitalDotComEndpoints;
 ///  * cst: the access token
 ///  * identifier: the email address you log in with
 ///  * password: the password you created for this API key
+///
+/// Threading:
+///  * CapitalDotComAPI is Send + Sync and can be shared across threads (e.g. in an Arc)
+///  * Methods taking &self can be called from multiple threads, the requests are executed one after another
+///  * open_session and switch_account take &mut self because they change the active account and need exclusive access
+///  * To switch accounts while sharing the client, wrap it in an RwLock: reads then never run during a switch
+///  * set_trading_enabled takes effect immediately for all threads
 #[derive(Debug)]
 pub struct CapitalDotComAPI {
     is_logged_in: Arc<Mutex<bool>>,
+    trading_enabled: AtomicBool,
+    dealing_enabled: bool,
+    trailing_stops_enabled: bool,
     capital_dot_com_endpoints: Arc<Mutex<endpoint::CapitalDotComApiEndpoints>>,
     runtime: tokio::runtime::Runtime,
+    request_timeout: Duration,
+    rate_limit_behavior: RateLimitBehavior,
 
+    session_type: SessionType,
     current_account_id: String,
+    streaming_host: Option<String>,
+    client_id: Option<String>,
+    has_active_demo_accounts: bool,
+    has_active_live_accounts: bool,
+    session_timezone: chrono::FixedOffset,
+    session_currency: Option<Currency>,
+    account_preferences: Mutex<Option<responses::AccountPreferencesResponse>>,
+    tradeable_markets: Mutex<Option<(std::time::Instant, Vec<responses::Market>)>>,
 }
 impl CapitalDotComAPI {
+    const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+    /// Minimum time between two requests (max of 10 requests per second)
+    const REQUEST_INTERVAL: Duration = Duration::from_millis(100);
+    /// Max number of epics per market search
+    const MAX_EPICS_PER_REQUEST: usize = 50;
+    /// How long open_position waits for a final confirmation
+    const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(5);
+    /// Time between two confirmation requests while waiting for a final confirmation
+    const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_millis(200);
+    /// Minimum time between two position/order requests
+    const ORDER_INTERVAL: Duration = Duration::from_millis(100);
+    /// Allowed difference between the local clock and the server clock when looking for duplicate positions.
+    const DUPLICATE_POSITION_TOLERANCE: chrono::TimeDelta = chrono::TimeDelta::seconds(10);
+    /// Max number of candles per price request
+    const MAX_PRICES_PER_REQUEST: usize = 1000;
+    /// How often get_recent_prices widens the time window (by RECENT_PRICES_WINDOW_GROWTH each time)
+    const RECENT_PRICES_ATTEMPTS: u32 = 5;
+    const RECENT_PRICES_WINDOW_GROWTH: i32 = 4;
+    /// How long the result of list_tradeable_markets is reused
+    const TRADEABLE_MARKETS_TTL: Duration = Duration::from_secs(60 * 60);
+    /// How far back reconcile looks for account activity
+    const RECONCILE_ACTIVITY_WINDOW: chrono::TimeDelta = chrono::TimeDelta::days(1);
+
+    /// Create a client with the default settings. Use CapitalDotComAPIBuilder to change them.
+    pub fn new(
+        session_type: SessionType,
+        x_cap_api_key: String,
+        identifier: String,
+        password: String,
+    ) -> Self {
+        CapitalDotComAPIBuilder::new(session_type, x_cap_api_key, identifier, password).build()
+    }
+
+    /// Create a client from a CapitalConfig, see CapitalConfig::from_json.
+    pub fn from_config(config: CapitalConfig) -> Self {
+        let mut builder = CapitalDotComAPIBuilder::new(
+            config.session_type,
+            config.api_key,
+            config.identifier,
+            config.password,
+        );
+        if let Some(base_url) = &config.base_url {
+            builder = builder.base_url(base_url);
+        }
+        if let Some(request_timeout_ms) = config.request_timeout_ms {
+            builder = builder.request_timeout(Duration::from_millis(request_timeout_ms));
+        }
+        if let Some(rate_limit_behavior) = config.rate_limit_behavior {
+            builder = builder.rate_limit_behavior(rate_limit_behavior);
+        }
+
+        let mut retry_policy = RetryPolicy::default();
+        if let Some(read_retry) = config.read_retry {
+            retry_policy.read = read_retry.into();
+        }
+        if let Some(order_retry) = config.order_retry {
+            retry_policy.order = order_retry.into();
+        }
+        if let Some(session_retry) = config.session_retry {
+            retry_policy.session = session_retry.into();
+        }
+        builder = builder.retry_policy(retry_policy);
+
+        if let Some(pool_max_idle_per_host) = config.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(pool_max_idle_per_host);
+        }
+        if let Some(pool_idle_timeout_ms) = config.pool_idle_timeout_ms {
+            builder = builder.pool_idle_timeout(Duration::from_millis(pool_idle_timeout_ms));
+        }
+
+        let capital_api = builder.build();
+        capital_api.set_trading_enabled(config.trading_enabled.unwrap_or(true));
+
+        capital_api
+    }
+
+    /// Maximum time a single call blocks before it returns CapitalDotComError::Timeout. Default = 30s
+    pub fn set_request_timeout(&mut self, request_timeout: Duration) {
+        self.request_timeout = request_timeout;
+    }
+
+    /// Behavior of the batch calls and send_authenticated when the server reported that no requests are left.
+    pub fn set_rate_limit_behavior(&mut self, rate_limit_behavior: RateLimitBehavior) {
+        self.rate_limit_behavior = rate_limit_behavior;
+    }
+
+    /// Authenticated request to any path (e.g. "/api/v1/accounts/preferences") returning the untyped json.
+    /// For endpoints and fields that are not supported yet.
+    /// GET requests use the read retries, all other methods the order retries.
+    /// Methods other than GET are blocked while trading is disabled.
+    pub fn call_raw(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<serde_json::Value>,
+    ) -> Result<serde_json::Value, CapitalDotComError> {
+        self.check_logged_in()?;
+        if method != Method::GET {
+            self.check_trading_enabled()?;
+        }
+
+        let mut capital_dot_com_endpoints_lock = self
+            .capital_dot_com_endpoints
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+
+        let (_, body) =
+            self.block_on(capital_dot_com_endpoints_lock.request_raw(method, path, body))?;
+
+        Ok(body)
+    }
+
+    /// Authenticated GET to any path, deserialized into a type of the caller.
+    /// For endpoints that are not supported yet, uses the read retries.
+    pub fn get_typed<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T, CapitalDotComError> {
+        self.request_typed(Method::GET, path, query, None::<()>)
+    }
+
+    /// Authenticated POST of body to any path, deserialized into a type of the caller.
+    /// For endpoints that are not supported yet, uses the order retries and is blocked while trading is disabled.
+    pub fn post_typed<T: DeserializeOwned, B: Serialize>(
+        &self,
+        path: &str,
+        body: B,
+    ) -> Result<T, CapitalDotComError> {
+        self.check_trading_enabled()?;
+
+        self.request_typed(Method::POST, path, &[], Some(body))
+    }
+
+    fn request_typed<T: DeserializeOwned, B: Serialize>(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Option<B>,
+    ) -> Result<T, CapitalDotComError> {
+        self.check_logged_in()?;
+
+        let mut capital_dot_com_endpoints_lock = self
+            .capital_dot_com_endpoints
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+
+        let (_, body) =
+            self.block_on(capital_dot_com_endpoints_lock.request_typed(method, path, query, body))?;
+
+        Ok(body)
+    }
+
+    /// Request to a path of the API (e.g. "/api/v1/positions") for send_authenticated.
+    pub fn request_builder(&self, method: Method, path: &str) -> reqwest::RequestBuilder {
+        let capital_dot_com_endpoints_lock = self
+            .capital_dot_com_endpoints
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+
+        capital_dot_com_endpoints_lock.request_builder(method, path)
+    }
+
+    /// Escape hatch for what the typed methods do not cover (streaming downloads, custom parsing, headers).
+    /// Adds the authorization headers, waits if the server reported that no requests are left (see RateLimitBehavior)
+    /// and returns the response as is.
+    /// The status code is not checked and the body is not read.
+    /// The body has to be read in an async context (e.g. a tokio runtime).
+    pub fn send_authenticated(
+        &self,
+        request_builder: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, CapitalDotComError> {
+        self.check_logged_in()?;
+        self.throttle(Duration::ZERO)?;
+
+        let mut capital_dot_com_endpoints_lock = self
+            .capital_dot_com_endpoints
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+
+        self.block_on(capital_dot_com_endpoints_lock.send_authenticated(request_builder))
+    }
+
+    /// Time windows of up to MAX_PRICES_PER_REQUEST candles between from and to.
+    /// With opening hours only the open sessions are covered, each from the start of the candle it opens in.
+    fn price_windows(
+        resolution: enums::Resolution,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+        opening_hours: Option<&responses::OpeningHours>,
+    ) -> Vec<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)> {
+        let ranges = opening_hours
+            .and_then(|opening_hours| opening_hours.open_ranges(from, to))
+            .unwrap_or_else(|| vec![(from, to)]);
+        let max_window = resolution.duration() * Self::MAX_PRICES_PER_REQUEST as i32;
+
+        let mut windows = Vec::new();
+        for (start, end) in ranges {
+            let seconds = start.timestamp();
+            let mut start = chrono::DateTime::from_timestamp(
+                seconds - seconds.rem_euclid(resolution.seconds()),
+                0,
+            )
+            .unwrap_or(start)
+            .max(from);
+
+            while start < end {
+                let window_end = (start + max_window).min(end);
+                windows.push((start, window_end));
+                start = window_end;
+            }
+        }
+
+        windows
+    }
+
+    /// Time zone of the times without UTC suffix, from the timezone_offset of the session. UTC before open_session.
+    /// Use with responses::parse_local_time.
+    pub fn session_timezone(&self) -> chrono::FixedOffset {
+        self.session_timezone
+    }
+
+    /// Currency of the active account from the session. None before open_session.
+    pub fn session_currency(&self) -> Option<Currency> {
+        self.session_currency
+    }
+
+    /// Client id of the last session, e.g. for logs and support requests. None before open_session.
+    pub fn client_id(&self) -> Option<&str> {
+        self.client_id.as_deref()
+    }
+
+    /// Host and tokens for the streaming API. None if there is no open session.
+    pub fn streaming_credentials(&self) -> Option<StreamingCredentials> {
+        self.check_logged_in().ok()?;
+        let host = self.streaming_host.clone()?;
+
+        let capital_dot_com_endpoints_lock = self
+            .capital_dot_com_endpoints
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+
+        Some(StreamingCredentials {
+            host,
+            cst: capital_dot_com_endpoints_lock.cst().to_string(),
+            security_token: capital_dot_com_endpoints_lock
+                .x_security_token()
+                .to_string(),
+        })
+    }
+
+    /// Rate limit state from the headers of the last response.
+    /// None if the server did not send any rate limit headers yet.
+    pub fn rate_limit_status(&self) -> Option<RateLimitStatus> {
+        let capital_dot_com_endpoints_lock = self
+            .capital_dot_com_endpoints
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+
+        capital_dot_com_endpoints_lock.rate_limit_status()
+    }
+
+    /// Per call alternative to RateLimitBehavior::Fail, e.g. before a latency sensitive call.
+    /// Errors with CapitalDotComError::RequestingTooFast and the time until the reset
+    /// if the server reported that no requests are left.
+    pub fn check_rate_limit(&self) -> Result<(), CapitalDotComError> {
+        match self.rate_limit_wait_time() {
+            Some(wait_time) => Err(CapitalDotComError::RequestingTooFast(
+                chrono::TimeDelta::from_std(wait_time).unwrap_or(chrono::TimeDelta::max_value()),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn rate_limit_wait_time(&self) -> Option<Duration> {
+        self.rate_limit_status()
+            .and_then(|rate_limit_status| rate_limit_status.wait_time(chrono::Utc::now()))
+    }
+
+    /// Wait the interval between two requests.
+    /// If the server reported that no requests are left, wait until its window resets instead
+    /// or fail with CapitalDotComError::RequestingTooFast, depending on the RateLimitBehavior.
+    fn throttle(&self, interval: Duration) -> Result<(), CapitalDotComError> {
+        if self.rate_limit_behavior == RateLimitBehavior::Fail {
+            self.check_rate_limit()?;
+        }
+
+        let wait_time = self
+            .rate_limit_wait_time()
+            .map_or(interval, |wait_time| wait_time.max(interval));
+        std::thread::sleep(wait_time);
+
+        Ok(())
+    }
+
+    pub fn session_type(&self) -> SessionType {
+        self.session_type
+    }
+
+    /// Guard against trading in the wrong environment, e.g. before placing orders.
+    /// Errors with CapitalDotComError::WrongEnvironment if the client is configured for another session type
+    /// or the session has no active account of the expected type.
+    pub fn assert_session_type(&self, expected: SessionType) -> Result<(), CapitalDotComError> {
+        self.check_logged_in()?;
+
+        if self.session_type != expected {
+            return Err(CapitalDotComError::WrongEnvironment(format!(
+                "expected a {} session, the client is configured for {}",
+                expected, self.session_type
+            )));
+        }
+
+        let has_active_accounts = match expected {
+            SessionType::Live => self.has_active_live_accounts,
+            SessionType::Demo => self.has_active_demo_accounts,
+        };
+        if !has_active_accounts {
+            return Err(CapitalDotComError::WrongEnvironment(format!(
+                "the session has no active {} accounts",
+                expected
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Kill switch for order placement. While disabled opening, closing and deleting positions and working orders
+    /// returns CapitalDotComError::TradingDisabled without sending a request, reads keep working.
+    /// Takes &self so a supervisor thread sharing the client can halt trading at any time. Default = enabled
+    pub fn set_trading_enabled(&self, trading_enabled: bool) {
+        self.trading_enabled
+            .store(trading_enabled, Ordering::SeqCst);
+    }
+
+    pub fn is_trading_enabled(&self) -> bool {
+        self.trading_enabled.load(Ordering::SeqCst)
+    }
+
+    /// Whether the broker allows dealing on the active account, from the response of switch_account.
+    /// Enabled after open_session.
+    pub fn is_dealing_enabled(&self) -> bool {
+        self.dealing_enabled
+    }
+
+    fn check_trading_enabled(&self) -> Result<(), CapitalDotComError> {
+        if !self.is_trading_enabled() {
+            return Err(CapitalDotComError::TradingDisabled);
+        }
+
+        if !self.dealing_enabled {
+            return Err(CapitalDotComError::DealingDisabled);
+        }
+
+        Ok(())
+    }
+
+    /// Whether the active account supports trailing stops, from open_session and switch_account.
+    pub fn trailing_stops_enabled(&self) -> bool {
+        self.trailing_stops_enabled
+    }
+
+    fn check_trailing_stop(&self, trailing_stop: bool) -> Result<(), CapitalDotComError> {
+        if trailing_stop && !self.trailing_stops_enabled {
+            return Err(CapitalDotComError::TrailingStopsDisabled);
+        }
+
+        Ok(())
+    }
+
+    /// Err(CapitalDotComError::NotLoggedIn) if open_session did not succeed yet.
+    fn check_logged_in(&self) -> Result<(), CapitalDotComError> {
+        let is_logged_in_lock = self.is_logged_in.lock().unwrap_or_else(|p| p.into_inner());
+
+        if *is_logged_in_lock {
+            Ok(())
+        } else {
+            Err(CapitalDotComError::NotLoggedIn)
+        }
+    }
+
+    /// Run the future on the runtime and cancel it when the request timeout expires.
+    fn block_on<T>(
+        &self,
+        future: impl Future<Output = Result<T, CapitalDotComError>>,
+    ) -> Result<T, CapitalDotComError> {
+        // The timeout needs to be created inside of the runtime.
+        match self
+            .runtime
+            .block_on(async { tokio::time::timeout(self.request_timeout, future).await })
+        {
+            Ok(result) => result,
+            Err(_) => Err(CapitalDotComError::Timeout(self.request_timeout)),
+        }
+    }
+}
+
+/// Trim the whitespace around an epic (e.g. from a config file).
+/// Epics that are empty or contain anything but letters, digits, '.', '_' and '-' are rejected with CapitalDotComError::InvalidEpic.
+/// The case is kept, some epics are mixed case.
+pub fn normalize_epic(epic: &str) -> Result<String, CapitalDotComError> {
+    let normalized = epic.trim();
+
+    let is_valid = !normalized.is_empty()
+        && normalized
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    if !is_valid {
+        return Err(CapitalDotComError::InvalidEpic(epic.to_string()));
+    }
+
+    Ok(normalized.to_string())
+}
+
+/// Result per deal_id of a batch operation (e.g. close_all_positions).
+pub type DealResults = Vec<(
+    String,
+    Result<responses::DealReferenceResponse, CapitalDotComError>,
+)>;
+
+/// An order that was accepted for processing (submit_position).
+/// Only the deal_reference is known at this point, the deal_id comes with the confirmation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingOrder {
+    pub deal_reference: String,
+}
+impl PendingOrder {
+    /// Wait up to 5 seconds for the final confirmation of the order.
+    /// A rejected order returns CapitalDotComError::OrderRejected, a failed confirmation
+    /// CapitalDotComError::ConfirmationFailed. Both carry the deal_reference.
+    pub fn confirm(
+        &self,
+        capital_api: &impl CapitalDotComInterface,
+    ) -> Result<ConfirmedOrder, CapitalDotComError> {
+        match capital_api
+            .await_confirmation(&self.deal_reference, CapitalDotComAPI::CONFIRMATION_TIMEOUT)
+        {
+            Ok(confirmation) if confirmation.is_rejected() => {
+                Err(CapitalDotComError::OrderRejected {
+                    deal_reference: self.deal_reference.clone(),
+                    reason: confirmation.reject_reason,
+                })
+            }
+            Ok(confirmation) => Ok(ConfirmedOrder {
+                deal_reference: self.deal_reference.clone(),
+                deal_id: confirmation.position_deal_id().to_string(),
+                confirmation,
+            }),
+            Err(e) => Err(CapitalDotComError::ConfirmationFailed {
+                deal_reference: self.deal_reference.clone(),
+                error: Box::new(e),
+            }),
+        }
+    }
+}
+
+/// An order with a final, accepted confirmation.
+/// The deal_id identifies the position (e.g. for close_position, see OrderConfirmationResponse::position_deal_id),
+/// the deal_reference the submitted order.
+#[derive(Debug)]
+pub struct ConfirmedOrder {
+    pub deal_reference: String,
+    pub deal_id: String,
+    pub confirmation: responses::OrderConfirmationResponse,
+}
+
+/// Everything needed to connect to the streaming API (WebSocket).
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamingCredentials {
+    pub host: String,
+    pub cst: String,
+    pub security_token: String,
+}
+
+pub struct CapitalDotComAPIBuilder {
+    session_type: SessionType,
+    x_cap_api_key: String,
+    identifier: String,
+    password: String,
+    request_timeout: Duration,
+    rate_limit_behavior: RateLimitBehavior,
+    retry_policy: RetryPolicy,
+    base_url: Option<String>,
+    pool_max_idle_per_host: Option<usize>,
+    pool_idle_timeout: Option<Duration>,
+}
+impl CapitalDotComAPIBuilder {
     pub fn new(
         session_type: SessionType,
         x_cap_api_key: String,
         identifier: String,
         password: String,
     ) -> Self {
+        Self {
+            session_type,
+            x_cap_api_key,
+            identifier,
+            password,
+            request_timeout: CapitalDotComAPI::DEFAULT_REQUEST_TIMEOUT,
+            rate_limit_behavior: RateLimitBehavior::default(),
+            retry_policy: RetryPolicy::default(),
+            base_url: None,
+            pool_max_idle_per_host: None,
+            pool_idle_timeout: None,
+        }
+    }
+
+    /// Maximum time a single call blocks before it returns CapitalDotComError::Timeout. Default = 30s
+    pub fn request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = request_timeout;
+
+        self
+    }
+
+    /// Whether the calls that pace their requests wait for the rate limit window or fail. Default = RateLimitBehavior::Wait
+    pub fn rate_limit_behavior(mut self, rate_limit_behavior: RateLimitBehavior) -> Self {
+        self.rate_limit_behavior = rate_limit_behavior;
+
+        self
+    }
+
+    /// Retries per request category. Default = RetryPolicy::default()
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+
+        self
+    }
+
+    /// Send all requests to this url instead of the url of the session type.
+    /// Meant for mock servers and proxies.
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = Some(base_url.to_string());
+
+        self
+    }
+
+    /// Maximum number of idle connections kept open to the API. Default = no limit
+    /// All calls share one connection pool. With the limit of 10 requests per second
+    /// a handful of connections is enough, e.g. 2 for sequential use or one per thread making concurrent calls.
+    pub fn pool_max_idle_per_host(mut self, pool_max_idle_per_host: usize) -> Self {
+        self.pool_max_idle_per_host = Some(pool_max_idle_per_host);
+
+        self
+    }
+
+    /// How long an idle connection is kept open. Default = 90s
+    /// Should be longer than the polling interval so polling reuses the connections.
+    pub fn pool_idle_timeout(mut self, pool_idle_timeout: Duration) -> Self {
+        self.pool_idle_timeout = Some(pool_idle_timeout);
+
+        self
+    }
+
+    pub fn build(self) -> CapitalDotComAPI {
         let runtime = match tokio::runtime::Runtime::new() {
             Ok(runtime) => runtime,
             Err(e) => panic!("Could not initialize runtime! Error: {}", e),
         };
 
-        Self {
+        // One client for all requests, so connections are reused.
+        let mut http_client_builder = reqwest::Client::builder();
+        if let Some(pool_max_idle_per_host) = self.pool_max_idle_per_host {
+            http_client_builder =
+                http_client_builder.pool_max_idle_per_host(pool_max_idle_per_host);
+        }
+        if let Some(pool_idle_timeout) = self.pool_idle_timeout {
+            http_client_builder = http_client_builder.pool_idle_timeout(pool_idle_timeout);
+        }
+        let http_client = match http_client_builder.build() {
+            Ok(http_client) => http_client,
+            Err(e) => panic!("Could not initialize http client! Error: {}", e),
+        };
+
+        let mut capital_dot_com_endpoints = CapitalDotComApiEndpoints::new(
+            self.session_type,
+            self.x_cap_api_key,
+            self.identifier,
+            self.password,
+            self.retry_policy,
+            http_client,
+        );
+        if let Some(base_url) = self.base_url {
+            capital_dot_com_endpoints.set_base_url(base_url);
+        }
+
+        CapitalDotComAPI {
             is_logged_in: Arc::new(Mutex::new(false)),
-            capital_dot_com_endpoints: Arc::new(Mutex::new(CapitalDotComApiEndpoints::new(
-                session_type,
-                x_cap_api_key,
-                identifier,
-                password,
-            ))),
+            trading_enabled: AtomicBool::new(true),
+            dealing_enabled: true,
+            trailing_stops_enabled: false,
+            capital_dot_com_endpoints: Arc::new(Mutex::new(capital_dot_com_endpoints)),
             runtime,
+            request_timeout: self.request_timeout,
+            rate_limit_behavior: self.rate_limit_behavior,
+
+            session_type: self.session_type,
+            current_account_id: String::new(),
+            streaming_host: None,
+            client_id: None,
+            has_active_demo_accounts: false,
+            has_active_live_accounts: false,
+            session_timezone: responses::timezone_from_offset(0),
+            session_currency: None,
+            account_preferences: Mutex::new(None),
+            tradeable_markets: Mutex::new(None),
+        }
+    }
+}
+
+/// All settings of CapitalDotComAPIBuilder in one struct, for reading them from a file (see from_json).
+/// Durations are in milliseconds, missing optional fields keep the defaults of the builder.
+/// ```json
+/// {
+///     "session_type": "Demo",
+///     "api_key": "...",
+///     "identifier": "user@example.com",
+///     "password": "...",
+///     "request_timeout_ms": 10000,
+///     "rate_limit_behavior": "Fail",
+///     "read_retry": {"retries": 3, "delay_ms": 500},
+///     "trading_enabled": false
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CapitalConfig {
+    pub session_type: SessionType,
+    pub api_key: String,
+    pub identifier: String,
+    pub password: String,
+    /// See CapitalDotComAPIBuilder::base_url
+    pub base_url: Option<String>,
+    pub request_timeout_ms: Option<u64>,
+    pub rate_limit_behavior: Option<RateLimitBehavior>,
+    /// Retries of RetryPolicy::read
+    pub read_retry: Option<RetryConfig>,
+    /// Retries of RetryPolicy::order
+    pub order_retry: Option<RetryConfig>,
+    /// Retries of RetryPolicy::session
+    pub session_retry: Option<RetryConfig>,
+    pub pool_max_idle_per_host: Option<usize>,
+    pub pool_idle_timeout_ms: Option<u64>,
+    /// false starts with order placement disabled (see set_trading_enabled), e.g. for a dry run. Default = true
+    pub trading_enabled: Option<bool>,
+}
+impl CapitalConfig {
+    /// Parse a config from JSON. Unknown fields are rejected so typos do not go unnoticed.
+    pub fn from_json(json: &str) -> Result<Self, CapitalDotComError> {
+        serde_json::from_str(json).map_err(CapitalDotComError::JsonError)
+    }
+}
+
+/// Retry in a CapitalConfig with the delay in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetryConfig {
+    pub retries: u32,
+    pub delay_ms: u64,
+}
+impl From<RetryConfig> for Retry {
+    fn from(retry_config: RetryConfig) -> Self {
+        Retry::new(
+            retry_config.retries,
+            Duration::from_millis(retry_config.delay_ms),
+        )
+    }
+}
+
+impl traits::CapitalDotComInterface for CapitalDotComAPI {
+    fn open_session(&mut self) -> Result<responses::CreateNewSessionResponse, CapitalDotComError> {
+        let mut capital_dot_com_endpoints_lock = self
+            .capital_dot_com_endpoints
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+
+        let (_, body) = self.block_on(capital_dot_com_endpoints_lock.create_new_session())?;
+        if body.accounts.is_empty() {
+            return Err(CapitalDotComError::NoAccountsAvailable);
+        }
+
+        self.current_account_id = body.current_account_id.clone();
+        self.streaming_host = Some(body.streaming_host.clone());
+        self.client_id = Some(body.client_id.clone());
+        self.session_timezone = body.timezone();
+        self.session_currency = Some(body.currency_iso_code);
+        self.dealing_enabled = true;
+        self.trailing_stops_enabled = body.trailing_stops_enabled;
+        self.has_active_demo_accounts = body.has_active_demo_accounts;
+        self.has_active_live_accounts = body.has_active_live_accounts;
+        *self
+            .account_preferences
+            .lock()
+            .unwrap_or_else(|p| p.into_inner()) = None;
+
+        let mut is_logged_in_lock = self.is_logged_in.lock().unwrap_or_else(|p| p.into_inner());
+        *is_logged_in_lock = true;
+
+        Ok(body)
+    }
+
+    fn refresh_session_details(
+        &mut self,
+    ) -> Result<responses::SessionDetailsResponse, CapitalDotComError> {
+        let session_details = self.get_session_details()?;
+
+        self.current_account_id = session_details.account_id.clone();
+        self.client_id = Some(session_details.client_id.clone());
+        self.session_currency = Some(session_details.currency);
+        self.session_timezone = session_details.timezone();
+        self.streaming_host = Some(session_details.stream_endpoint.clone());
+
+        Ok(session_details)
+    }
+
+    fn get_session_details(&self) -> Result<responses::SessionDetailsResponse, CapitalDotComError> {
+        self.check_logged_in()?;
+
+        let mut capital_dot_com_endpoints_lock = self
+            .capital_dot_com_endpoints
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+
+        let (_, body) = self.block_on(capital_dot_com_endpoints_lock.get_session_details())?;
+
+        Ok(body)
+    }
+
+    fn get_balance(&self) -> Result<responses::BalanceAccountInfo, CapitalDotComError> {
+        self.check_logged_in()?;
+
+        let mut capital_dot_com_endpoints_lock = self
+            .capital_dot_com_endpoints
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+
+        let (_, body) = self.block_on(capital_dot_com_endpoints_lock.get_all_accounts())?;
+
+        for account in body.accounts {
+            if account.account_id == self.current_account_id {
+                return Ok(account.balance);
+            }
+        }
+
+        Err(CapitalDotComError::CurrentAccountNotFound)
+    }
+
+    fn balance_changed_since(
+        &self,
+        previous: &responses::BalanceAccountInfo,
+    ) -> Result<Option<responses::BalanceAccountInfo>, CapitalDotComError> {
+        let balance = self.get_balance()?;
+
+        Ok((balance != *previous).then_some(balance))
+    }
+
+    fn get_all_accounts(&self) -> Result<responses::AllAccountsResponse, CapitalDotComError> {
+        self.check_logged_in()?;
+
+        let mut capital_dot_com_endpoints_lock = self
+            .capital_dot_com_endpoints
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+
+        let (_, body) = self.block_on(capital_dot_com_endpoints_lock.get_all_accounts())?;
+
+        Ok(body)
+    }
+
+    fn account_currencies(&self) -> Result<Vec<enums::Currency>, CapitalDotComError> {
+        Ok(self.get_all_accounts()?.currencies())
+    }
+
+    fn account_summary(&self) -> Result<responses::AccountSummary, CapitalDotComError> {
+        let balance = self.get_balance()?;
+        self.throttle(Self::REQUEST_INTERVAL)?;
+        let all_positions = self.get_all_positions()?;
+
+        Ok(responses::AccountSummary {
+            balance: balance.balance,
+            available: balance.available,
+            open_pl: all_positions
+                .positions
+                .iter()
+                .map(|position| position.position.upl)
+                .sum(),
+            position_count: all_positions.positions.len(),
+            margin_used: all_positions
+                .positions
+                .iter()
+                .map(|position| position.margin())
+                .sum(),
+        })
+    }
+
+    fn reconcile(&self) -> Result<responses::ReconciliationSnapshot, CapitalDotComError> {
+        let positions = self.get_all_positions()?.positions;
+        self.throttle(Self::REQUEST_INTERVAL)?;
+        let working_orders = self.get_all_working_orders()?.working_orders;
+        self.throttle(Self::REQUEST_INTERVAL)?;
+        let to = chrono::Utc::now();
+        let recent_activity = self
+            .get_activity_history(to - Self::RECONCILE_ACTIVITY_WINDOW, to)?
+            .activities;
+
+        Ok(responses::ReconciliationSnapshot {
+            positions,
+            working_orders,
+            recent_activity,
+        })
+    }
+
+    fn switch_account(
+        &mut self,
+        account_id: &str,
+    ) -> Result<responses::SwitchAccountResponse, CapitalDotComError> {
+        self.check_logged_in()?;
+
+        if account_id == self.current_account_id {
+            return Err(CapitalDotComError::NotDifferentAccountId);
+        }
+
+        let mut capital_dot_com_endpoints_lock = self
+            .capital_dot_com_endpoints
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+
+        let (_, body) =
+            self.block_on(capital_dot_com_endpoints_lock.switch_active_account(account_id))?;
+        drop(capital_dot_com_endpoints_lock);
+        // The server switched, all following calls are for the new account even if the confirmation fails
+        self.current_account_id = account_id.to_string();
+        self.dealing_enabled = body.dealing_enabled;
+        self.trailing_stops_enabled = body.trailing_stops_enabled;
+
+        // The cached preferences belong to the previous account
+        *self
+            .account_preferences
+            .lock()
+            .unwrap_or_else(|p| p.into_inner()) = None;
+
+        // Only return once the server reports the new account as active
+        self.refresh_session_details()?;
+        if self.current_account_id != account_id {
+            return Err(CapitalDotComError::AccountSwitchNotConfirmed(
+                self.current_account_id.clone(),
+            ));
+        }
+
+        Ok(body)
+    }
+
+    fn get_account_preferences(
+        &self,
+    ) -> Result<responses::AccountPreferencesResponse, CapitalDotComError> {
+        self.check_logged_in()?;
+
+        let mut capital_dot_com_endpoints_lock = self
+            .capital_dot_com_endpoints
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+
+        let (_, body) = self.block_on(capital_dot_com_endpoints_lock.get_account_preferences())?;
+
+        *self
+            .account_preferences
+            .lock()
+            .unwrap_or_else(|p| p.into_inner()) = Some(body.clone());
+
+        Ok(body)
+    }
+
+    fn get_leverage_for(&self, instrument_group: &str) -> Option<responses::Leverage> {
+        let account_preferences_lock = self
+            .account_preferences
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+
+        account_preferences_lock
+            .as_ref()?
+            .leverages
+            .get(instrument_group)
+            .cloned()
+    }
+
+    fn close_session(&self) -> Result<responses::SessionLogOutResponse, CapitalDotComError> {
+        self.check_logged_in()?;
+
+        let mut capital_dot_com_endpoints_lock = self
+            .capital_dot_com_endpoints
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+
+        let (_, body) = self.block_on(capital_dot_com_endpoints_lock.session_log_out())?;
+
+        if body.status != responses::LogoutStatus::SUCCESS {
+            return Err(CapitalDotComError::LogoutFailed);
+        }
+
+        let mut is_logged_in_lock = self.is_logged_in.lock().unwrap_or_else(|p| p.into_inner());
+        *is_logged_in_lock = false;
+
+        Ok(body)
+    }
+
+    fn search_market(
+        &self,
+        search_term: Option<&str>,
+        epic: Vec<String>,
+    ) -> Result<responses::MarketDetailsResponse, CapitalDotComError> {
+        self.check_logged_in()?;
+        let epic = epic
+            .iter()
+            .map(|epic| normalize_epic(epic))
+            .collect::<Result<Vec<String>, CapitalDotComError>>()?;
+
+        let mut capital_dot_com_endpoints_lock = self
+            .capital_dot_com_endpoints
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+
+        let (_, body) =
+            self.block_on(capital_dot_com_endpoints_lock.get_market_details(search_term, epic))?;
+
+        Ok(body)
+    }
+
+    fn search_market_chunked(
+        &self,
+        search_term: Option<&str>,
+        epics: Vec<String>,
+    ) -> Result<responses::MarketDetailsResponse, CapitalDotComError> {
+        if epics.len() <= Self::MAX_EPICS_PER_REQUEST {
+            return self.search_market(search_term, epics);
+        }
+
+        let mut markets = Vec::new();
+        for (i, chunk) in epics.chunks(Self::MAX_EPICS_PER_REQUEST).enumerate() {
+            if i > 0 {
+                self.throttle(Self::REQUEST_INTERVAL)?;
+            }
+
+            let response = self.search_market(search_term, chunk.to_vec())?;
+            markets.extend(response.markets);
+        }
+
+        Ok(responses::MarketDetailsResponse { markets })
+    }
+
+    fn get_market_navigation(
+        &self,
+        node_id: Option<&str>,
+    ) -> Result<responses::MarketNavigationResponse, CapitalDotComError> {
+        self.check_logged_in()?;
+
+        let mut capital_dot_com_endpoints_lock = self
+            .capital_dot_com_endpoints
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+
+        let (_, body) =
+            self.block_on(capital_dot_com_endpoints_lock.get_market_navigation(node_id))?;
+
+        Ok(body)
+    }
+
+    fn list_tradeable_markets(&self) -> Result<Vec<responses::Market>, CapitalDotComError> {
+        // Hold the lock while walking so concurrent callers wait for the result instead of walking again.
+        let mut tradeable_markets_lock = self
+            .tradeable_markets
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+
+        if let Some((fetched_at, markets)) = tradeable_markets_lock.as_ref() {
+            if fetched_at.elapsed() < Self::TRADEABLE_MARKETS_TTL {
+                return Ok(markets.clone());
+            }
+        }
+
+        let mut nodes: Vec<String> = self
+            .get_market_navigation(None)?
+            .nodes
+            .into_iter()
+            .map(|node| node.id)
+            .collect();
+        let mut visited_nodes = HashSet::new();
+        let mut epics = HashSet::new();
+        let mut markets = Vec::new();
+
+        while let Some(node_id) = nodes.pop() {
+            if !visited_nodes.insert(node_id.clone()) {
+                continue;
+            }
+
+            self.throttle(Self::REQUEST_INTERVAL)?;
+            let navigation = self.get_market_navigation(Some(&node_id))?;
+
+            nodes.extend(navigation.nodes.into_iter().map(|node| node.id));
+            for market in navigation.markets {
+                // Markets can be listed in multiple nodes
+                if market.market_status.is_tradeable() && epics.insert(market.epic.clone()) {
+                    markets.push(market);
+                }
+            }
+        }
+
+        *tradeable_markets_lock = Some((std::time::Instant::now(), markets.clone()));
+
+        Ok(markets)
+    }
+
+    fn search_markets_by_type(
+        &self,
+        instrument_type: enums::InstrumentType,
+    ) -> Result<Vec<responses::Market>, CapitalDotComError> {
+        Ok(self
+            .list_tradeable_markets()?
+            .into_iter()
+            .filter(|market| market.instrument_type == instrument_type)
+            .collect())
+    }
+
+    fn current_margin_requirement(
+        &self,
+        epic: &str,
+    ) -> Result<responses::MarginInfo, CapitalDotComError> {
+        let market = self.get_market_data(epic)?;
+        self.throttle(Self::REQUEST_INTERVAL)?;
+        let balance = self.get_balance()?;
+
+        Ok(market.margin_info(balance.available))
+    }
+
+    fn get_market_data(
+        &self,
+        epic: &str,
+    ) -> Result<responses::SingleMarketDetailsResponse, CapitalDotComError> {
+        self.check_logged_in()?;
+        let epic = normalize_epic(epic)?;
+
+        let mut capital_dot_com_endpoints_lock = self
+            .capital_dot_com_endpoints
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+
+        let (_, body) =
+            self.block_on(capital_dot_com_endpoints_lock.get_single_market_details(epic))?;
+
+        Ok(body)
+    }
+
+    fn get_all_positions(&self) -> Result<responses::AllPositionsResponse, CapitalDotComError> {
+        self.check_logged_in()?;
+
+        let mut capital_dot_com_endpoints_lock = self
+            .capital_dot_com_endpoints
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+
+        let (_, body) = self.block_on(capital_dot_com_endpoints_lock.get_all_positions())?;
+
+        Ok(body)
+    }
+
+    fn positions_with_risk(&self) -> Result<Vec<responses::PositionRisk>, CapitalDotComError> {
+        Ok(self
+            .get_all_positions()?
+            .positions
+            .iter()
+            .map(|position| position.risk())
+            .collect())
+    }
+
+    fn open_position(
+        &self,
+        position_data: request_bodies::CreatePositionBody,
+    ) -> Result<responses::OrderConfirmationResponse, CapitalDotComError> {
+        let (_, confirmation) = self.open_position_detailed(position_data)?;
+
+        Ok(confirmation)
+    }
+
+    fn open_position_detailed(
+        &self,
+        position_data: request_bodies::CreatePositionBody,
+    ) -> Result<
+        (
+            responses::DealReferenceResponse,
+            responses::OrderConfirmationResponse,
+        ),
+        CapitalDotComError,
+    > {
+        let confirmed_order = self.submit_position(position_data)?.confirm(self)?;
+
+        Ok((
+            responses::DealReferenceResponse {
+                deal_reference: confirmed_order.deal_reference,
+            },
+            confirmed_order.confirmation,
+        ))
+    }
+
+    fn submit_position(
+        &self,
+        position_data: request_bodies::CreatePositionBody,
+    ) -> Result<PendingOrder, CapitalDotComError> {
+        self.check_logged_in()?;
+        self.check_trading_enabled()?;
+        self.check_trailing_stop(position_data.has_trailing_stop())?;
+
+        let mut capital_dot_com_endpoints_lock = self
+            .capital_dot_com_endpoints
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+
+        let (_, body) =
+            self.block_on(capital_dot_com_endpoints_lock.open_position(position_data))?;
+
+        Ok(PendingOrder {
+            deal_reference: body.deal_reference,
+        })
+    }
+
+    fn min_order_body(
+        &self,
+        epic: &str,
+        direction: enums::Direction,
+    ) -> Result<request_bodies::CreatePositionBody, CapitalDotComError> {
+        let market = self.get_market_data(epic)?;
+
+        request_bodies::CreatePositionBodyBuilder::new(
+            direction,
+            epic,
+            market.dealing_rules.min_deal_size.value,
+        )
+        .build()
+    }
+
+    fn place_bracket_order(
+        &self,
+        direction: enums::Direction,
+        epic: &str,
+        size: f64,
+        stop_distance: f64,
+        profit_distance: f64,
+    ) -> Result<responses::OrderConfirmationResponse, CapitalDotComError> {
+        let market = self.get_market_data(epic)?;
+        let price = market.snapshot.opening_price(direction);
+
+        for distance in [stop_distance, profit_distance] {
+            if !market
+                .dealing_rules
+                .is_valid_stop_or_profit_distance_at(distance, price)
+            {
+                return Err(CapitalDotComError::InvalidDistance(distance));
+            }
+        }
+
+        let position_data = request_bodies::CreatePositionBodyBuilder::new(direction, epic, size)
+            .stop_distance(stop_distance)
+            .profit_distance(profit_distance)
+            .build()?;
+
+        self.throttle(Self::ORDER_INTERVAL)?;
+        self.open_position(position_data)
+    }
+
+    fn open_position_risk_based(
+        &self,
+        direction: enums::Direction,
+        epic: &str,
+        risk_fraction: f64,
+        stop_distance: f64,
+    ) -> Result<(f64, responses::OrderConfirmationResponse), CapitalDotComError> {
+        if risk_fraction <= 0. || risk_fraction > 1. {
+            return Err(CapitalDotComError::InvalidRiskFraction(risk_fraction));
+        }
+
+        let balance = self.get_balance()?;
+        self.throttle(Self::REQUEST_INTERVAL)?;
+        let market = self.get_market_data(epic)?;
+
+        if !market.dealing_rules.is_valid_stop_or_profit_distance_at(
+            stop_distance,
+            market.snapshot.opening_price(direction),
+        ) {
+            return Err(CapitalDotComError::InvalidDistance(stop_distance));
+        }
+
+        // Loss at the stop = size * stop_distance * value_per_point, like PositionResponse::computed_pl
+        let risk_amount = balance.available * risk_fraction;
+        let size = market
+            .dealing_rules
+            .round_size(risk_amount / (stop_distance * market.value_per_point()));
+
+        if !market.dealing_rules.is_valid_deal_size(size) {
+            return Err(CapitalDotComError::InvalidDealSize(size));
+        }
+
+        let position_data = request_bodies::CreatePositionBodyBuilder::new(direction, epic, size)
+            .stop_distance(stop_distance)
+            .build()?;
+
+        self.throttle(Self::ORDER_INTERVAL)?;
+        Ok((size, self.open_position(position_data)?))
+    }
+
+    fn open_position_with_retries(
+        &self,
+        position_data: request_bodies::CreatePositionBody,
+        retries: u32,
+    ) -> Result<responses::OrderConfirmationResponse, CapitalDotComError> {
+        let first_attempt = chrono::Utc::now() - Self::DUPLICATE_POSITION_TOLERANCE;
+
+        let mut attempt = 0;
+        loop {
+            match self.open_position(position_data.clone()) {
+                Err(e) if e.is_transient() && attempt < retries => {
+                    // The position may have been opened even though the response got lost.
+                    let all_positions = self.get_all_positions()?;
+                    for position in all_positions.positions {
+                        let is_new = match position.position.created_at() {
+                            Some(created_at) => created_at >= first_attempt,
+                            None => true,
+                        };
+
+                        if is_new && position_data.matches(&position) {
+                            return Err(CapitalDotComError::PositionAlreadyOpened(
+                                position.position.deal_id,
+                            ));
+                        }
+                    }
+
+                    attempt += 1;
+                    self.throttle(Self::ORDER_INTERVAL)?;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn await_confirmation(
+        &self,
+        deal_reference: &str,
+        timeout: Duration,
+    ) -> Result<responses::OrderConfirmationResponse, CapitalDotComError> {
+        let deadline = std::time::Instant::now() + timeout;
+
+        loop {
+            match self.order_confirmation(deal_reference) {
+                Ok(confirmation) if !confirmation.is_pending() => return Ok(confirmation),
+                Ok(_) => {}
+                // The confirmation is not available yet
+                Err(e) if e.status() == Some(404) || e.is_transient() => {}
+                Err(e) => return Err(e),
+            }
+
+            if std::time::Instant::now() + Self::CONFIRMATION_POLL_INTERVAL > deadline {
+                return Err(CapitalDotComError::Timeout(timeout));
+            }
+            std::thread::sleep(Self::CONFIRMATION_POLL_INTERVAL);
+        }
+    }
+
+    fn order_confirmation(
+        &self,
+        deal_reference: &str,
+    ) -> Result<responses::OrderConfirmationResponse, CapitalDotComError> {
+        self.check_logged_in()?;
+
+        let mut capital_dot_com_endpoints_lock = self
+            .capital_dot_com_endpoints
+            .lock()
+            .unwrap_or_else(|p| p.into_inner());
+
+        let (_, body) =
+            self.block_on(capital_dot_com_endpoints_lock.order_confirmation(deal_reference))?;
 
-            current_account_id: String::new(),
-        }
+        Ok(body)
     }
-}
 
-impl traits::CapitalDotComInterface for CapitalDotComAPI {
-    fn open_session(&mut self) -> Result<responses::CreateNewSessionResponse, CapitalDotComError> {
+    fn get_position_data(
+        &self,
+        deal_id: &str,
+    ) -> Result<responses::PositionResponse, CapitalDotComError> {
+        self.check_logged_in()?;
+
         let mut capital_dot_com_endpoints_lock = self
             .capital_dot_com_endpoints
             .lock()
             .unwrap_or_else(|p| p.into_inner());
 
-        let (_, body) = self
-            .runtime
-            .block_on(capital_dot_com_endpoints_lock.create_new_session())?;
-
-        self.current_account_id = body.current_account_id.clone();
+        let (_, body) =
+            self.block_on(capital_dot_com_endpoints_lock.get_position(deal_id.to_string()))?;
 
         Ok(body)
     }
 
-    fn get_session_details(&self) -> Result<responses::SessionDetailsResponse, CapitalDotComError> {
-        let mut is_logged_in_lock = self.is_logged_in.lock().unwrap_or_else(|p| p.into_inner());
-        *is_logged_in_lock = true;
+    fn is_epic_tradeable(&self, epic: &str) -> Result<bool, CapitalDotComError> {
+        Ok(self.get_market_data(epic)?.snapshot.is_tradeable())
+    }
+
+    fn position_with_fresh_market(
+        &self,
+        deal_id: &str,
+    ) -> Result<
+        (
+            responses::PositionResponse,
+            responses::SingleMarketDetailsResponse,
+        ),
+        CapitalDotComError,
+    > {
+        let position = self.get_position_data(deal_id)?;
+        self.throttle(Self::REQUEST_INTERVAL)?;
+        let market = self.get_market_data(&position.market.epic)?;
+
+        Ok((position, market))
+    }
+
+    fn update_position(
+        &self,
+        position: &responses::PositionResponse,
+        position_update_data: request_bodies::PositionUpdateBody,
+    ) -> Result<responses::DealReferenceResponse, CapitalDotComError> {
+        self.check_logged_in()?;
+        self.check_trading_enabled()?;
+        self.check_trailing_stop(position_update_data.has_trailing_stop())?;
+
+        if !position_update_data.changes(&position.position) {
+            return Err(CapitalDotComError::NoChanges);
+        }
 
         let mut capital_dot_com_endpoints_lock = self
             .capital_dot_com_endpoints
             .lock()
             .unwrap_or_else(|p| p.into_inner());
 
-        let (_, body) = self
-            .runtime
-            .block_on(capital_dot_com_endpoints_lock.get_session_details())?;
+        let (_, body) = self.block_on(
+            capital_dot_com_endpoints_lock
+                .update_position(position.position.deal_id.clone(), position_update_data),
+        )?;
 
         Ok(body)
     }
 
-    fn get_balance(&self) -> Result<responses::BalanceAccountInfo, CapitalDotComError> {
+    fn close_position(
+        &self,
+        deal_id: &str,
+    ) -> Result<responses::DealReferenceResponse, CapitalDotComError> {
+        self.check_logged_in()?;
+        self.check_trading_enabled()?;
+
         let mut capital_dot_com_endpoints_lock = self
             .capital_dot_com_endpoints
             .lock()
             .unwrap_or_else(|p| p.into_inner());
 
-        let (_, body) = self
-            .runtime
-            .block_on(capital_dot_com_endpoints_lock.get_all_accounts())?;
+        let (_, body) =
+            self.block_on(capital_dot_com_endpoints_lock.close_position(deal_id.to_string()))?;
 
-        for account in body.accounts {
-            if account.account_id == self.current_account_id {
-                return Ok(account.balance);
+        Ok(body)
+    }
+
+    fn close_position_confirmed(&self, deal_id: &str) -> Result<(), CapitalDotComError> {
+        match self.close_position(deal_id) {
+            Ok(body) => {
+                // The position check below is authoritative, only a rejection ends here.
+                if let Ok(confirmation) =
+                    self.await_confirmation(&body.deal_reference, Self::CONFIRMATION_TIMEOUT)
+                {
+                    if confirmation.is_rejected() {
+                        return Err(CapitalDotComError::OrderRejected {
+                            deal_reference: body.deal_reference,
+                            reason: confirmation.reject_reason,
+                        });
+                    }
+                }
             }
+            Err(e) if !e.is_transient() => return Err(e),
+            Err(_) => {}
         }
 
-        Err(CapitalDotComError::CurrentAccountNotFound)
+        self.throttle(Self::ORDER_INTERVAL)?;
+        match self.get_position_data(deal_id) {
+            Ok(position) if position.position.size > 0. => {
+                Err(CapitalDotComError::PositionStillOpen(deal_id.to_string()))
+            }
+            Ok(_) => Ok(()),
+            Err(e) if e.status() == Some(404) => Ok(()),
+            Err(e) => Err(e),
+        }
     }
 
-    fn get_all_accounts(&self) -> Result<responses::AllAccountsResponse, CapitalDotComError> {
+    fn close_position_with(
+        &self,
+        deal_id: &str,
+        close_position_data: request_bodies::ClosePositionBody,
+    ) -> Result<responses::OrderConfirmationResponse, CapitalDotComError> {
+        self.check_logged_in()?;
+        self.check_trading_enabled()?;
+
+        match (close_position_data.order_type, close_position_data.level) {
+            (None, Some(_)) => {
+                return Err(CapitalDotComError::ConflictingOrderParameters(
+                    String::from("level needs an order_type"),
+                ))
+            }
+            (Some(_), None) => {
+                return Err(CapitalDotComError::ConflictingOrderParameters(
+                    String::from("order_type needs a level"),
+                ))
+            }
+            _ => {}
+        }
+
+        let position = self.get_position_data(deal_id)?;
+        self.throttle(Self::REQUEST_INTERVAL)?;
+        if self.get_account_preferences()?.hedging_mode {
+            return Err(CapitalDotComError::HedgingModeEnabled);
+        }
+
+        let size = close_position_data.size.unwrap_or(position.position.size);
+        if size <= 0. || size > position.position.size {
+            return Err(CapitalDotComError::InvalidDealSize(size));
+        }
+        let direction = position.position.direction.opposite();
+
+        self.throttle(Self::ORDER_INTERVAL)?;
+        let (Some(order_type), Some(level)) =
+            (close_position_data.order_type, close_position_data.level)
+        else {
+            let position_data = request_bodies::CreatePositionBodyBuilder::new(
+                direction,
+                &position.market.epic,
+                size,
+            )
+            .build()?;
+
+            return self.open_position(position_data);
+        };
+
         let mut capital_dot_com_endpoints_lock = self
             .capital_dot_com_endpoints
             .lock()
             .unwrap_or_else(|p| p.into_inner());
 
-        let (_, body) = self
-            .runtime
-            .block_on(capital_dot_com_endpoints_lock.get_all_accounts())?;
+        let (_, body) = self.block_on(capital_dot_com_endpoints_lock.create_working_order(
+            request_bodies::CreateWorkingOrderBody::new(
+                direction,
+                &position.market.epic,
+                size,
+                level,
+                order_type,
+            ),
+        ))?;
+        drop(capital_dot_com_endpoints_lock);
+
+        Ok(PendingOrder {
+            deal_reference: body.deal_reference,
+        }
+        .confirm(self)?
+        .confirmation)
+    }
 
-        Ok(body)
+    fn close_all_positions(&self) -> Result<DealResults, CapitalDotComError> {
+        let all_positions = self.get_all_positions()?;
+
+        let mut results = Vec::new();
+        for position in all_positions.positions {
+            self.throttle(Self::ORDER_INTERVAL)?;
+
+            let result = self.close_position(&position.position.deal_id);
+            results.push((position.position.deal_id, result));
+        }
+
+        Ok(results)
     }
 
-    fn switch_account(
-        &mut self,
-        account_id: &str,
-    ) -> Result<responses::SwitchAccountResponse, CapitalDotComError> {
-        if account_id == self.current_account_id {
-            return Err(CapitalDotComError::NotDifferentAccountId);
+    fn hedge_position(
+        &self,
+        deal_id: &str,
+    ) -> Result<responses::OrderConfirmationResponse, CapitalDotComError> {
+        self.check_trading_enabled()?;
+
+        let position = self.get_position_data(deal_id)?;
+        self.throttle(Self::REQUEST_INTERVAL)?;
+
+        if !self.get_account_preferences()?.hedging_mode {
+            return Err(CapitalDotComError::HedgingModeDisabled);
         }
 
-        let mut capital_dot_com_endpoints_lock = self
-            .capital_dot_com_endpoints
-            .lock()
-            .unwrap_or_else(|p| p.into_inner());
+        self.throttle(Self::ORDER_INTERVAL)?;
+        self.open_position(position.reverse_body())
+    }
 
-        let (_, body) = self
-            .runtime
-            .block_on(capital_dot_com_endpoints_lock.switch_active_account(account_id))?;
+    fn reverse_position(
+        &self,
+        deal_id: &str,
+    ) -> Result<responses::OrderConfirmationResponse, CapitalDotComError> {
+        self.check_trading_enabled()?;
 
-        self.current_account_id = account_id.to_string();
+        let position = self.get_position_data(deal_id)?;
 
-        Ok(body)
+        self.close_position_confirmed(deal_id)?;
+        self.throttle(Self::ORDER_INTERVAL)?;
+
+        self.open_position(position.reverse_body())
     }
 
-    fn close_session(&self) -> Result<responses::SessionLogOutResponse, CapitalDotComError> {
-        let mut is_logged_in_lock = self.is_logged_in.lock().unwrap_or_else(|p| p.into_inner());
-        *is_logged_in_lock = false;
+    fn add_to_position(
+        &self,
+        deal_id: &str,
+        additional_size: f64,
+    ) -> Result<responses::OrderConfirmationResponse, CapitalDotComError> {
+        self.check_trading_enabled()?;
 
-        let mut capital_dot_com_endpoints_lock = self
-            .capital_dot_com_endpoints
-            .lock()
-            .unwrap_or_else(|p| p.into_inner());
+        let (position, market) = self.position_with_fresh_market(deal_id)?;
+        if !market.dealing_rules.is_valid_deal_size(additional_size) {
+            return Err(CapitalDotComError::InvalidDealSize(additional_size));
+        }
 
-        let (_, body) = self
-            .runtime
-            .block_on(capital_dot_com_endpoints_lock.session_log_out())?;
+        let position_data = request_bodies::CreatePositionBodyBuilder::new(
+            position.position.direction,
+            &position.market.epic,
+            additional_size,
+        )
+        .build()?;
 
-        Ok(body)
+        self.throttle(Self::ORDER_INTERVAL)?;
+        self.open_position(position_data)
     }
 
-    fn search_market(
+    fn get_all_working_orders(
         &self,
-        search_term: &str,
-        epic: Vec<String>,
-    ) -> Result<responses::MarketDetailsResponse, CapitalDotComError> {
+    ) -> Result<responses::AllWorkingOrdersResponse, CapitalDotComError> {
+        self.check_logged_in()?;
+
         let mut capital_dot_com_endpoints_lock = self
             .capital_dot_com_endpoints
             .lock()
             .unwrap_or_else(|p| p.into_inner());
 
-        let (_, body) = self
-            .runtime
-            .block_on(capital_dot_com_endpoints_lock.get_market_details(search_term, epic))?;
+        let (_, body) = self.block_on(capital_dot_com_endpoints_lock.get_all_working_orders())?;
 
         Ok(body)
     }
 
-    fn get_market_data(
+    fn get_working_order(
         &self,
-        epic: &str,
-    ) -> Result<responses::SingleMarketDetailsResponse, CapitalDotComError> {
+        deal_id: &str,
+    ) -> Result<responses::WorkingOrder, CapitalDotComError> {
+        let working_orders = self.get_all_working_orders()?;
+
+        working_orders
+            .working_orders
+            .into_iter()
+            .find(|working_order| working_order.working_order_data.deal_id == deal_id)
+            .ok_or(CapitalDotComError::WorkingOrderNotFound)
+    }
+
+    fn delete_working_order(
+        &self,
+        deal_id: &str,
+    ) -> Result<responses::DealReferenceResponse, CapitalDotComError> {
+        self.check_logged_in()?;
+        self.check_trading_enabled()?;
+
         let mut capital_dot_com_endpoints_lock = self
             .capital_dot_com_endpoints
             .lock()
             .unwrap_or_else(|p| p.into_inner());
 
-        let (_, body) = self
-            .runtime
-            .block_on(capital_dot_com_endpoints_lock.get_single_market_details(epic.to_string()))?;
+        let (_, body) =
+            self.block_on(capital_dot_com_endpoints_lock.delete_working_order(deal_id))?;
 
         Ok(body)
     }
 
-    fn get_all_positions(&self) -> Result<responses::AllPositionsResponse, CapitalDotComError> {
-        let mut capital_dot_com_endpoints_lock = self
-            .capital_dot_com_endpoints
-            .lock()
-            .unwrap_or_else(|p| p.into_inner());
+    fn cancel_all_working_orders(&self) -> Result<DealResults, CapitalDotComError> {
+        let all_working_orders = self.get_all_working_orders()?;
 
-        let (_, body) = self
-            .runtime
-            .block_on(capital_dot_com_endpoints_lock.get_all_positions())?;
+        let mut results = Vec::new();
+        for working_order in all_working_orders.working_orders {
+            self.throttle(Self::ORDER_INTERVAL)?;
 
-        Ok(body)
+            let deal_id = working_order.working_order_data.deal_id;
+            let result = self.delete_working_order(&deal_id);
+            results.push((deal_id, result));
+        }
+
+        Ok(results)
     }
 
-    fn open_position(
+    fn get_activity_history(
         &self,
-        position_data: request_bodies::CreatePositionBody,
-    ) -> Result<responses::OrderConfirmationResponse, CapitalDotComError> {
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+    ) -> Result<responses::ActivityHistoryResponse, CapitalDotComError> {
+        self.check_logged_in()?;
+
         let mut capital_dot_com_endpoints_lock = self
             .capital_dot_com_endpoints
             .lock()
             .unwrap_or_else(|p| p.into_inner());
 
-        let (_, body) = self
-            .runtime
-            .block_on(capital_dot_com_endpoints_lock.open_position(position_data))?;
-
-        let (_, body) = self
-            .runtime
-            .block_on(capital_dot_com_endpoints_lock.order_confirmation(&body.deal_reference))?;
+        let (_, body) =
+            self.block_on(capital_dot_com_endpoints_lock.get_activity_history(from, to))?;
 
         Ok(body)
     }
 
-    /// CURRENTLY DOES NOT WORK
-    fn get_position_data(
+    fn positions_opened_between(
         &self,
-        deal_id: &str,
-    ) -> Result<responses::PositionResponse, CapitalDotComError> {
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<responses::Activity>, CapitalDotComError> {
+        let activity_history = self.get_activity_history(from, to)?;
+
+        Ok(activity_history
+            .activities
+            .into_iter()
+            .filter(|activity| activity.is_position_opened())
+            .collect())
+    }
+
+    fn get_transaction_history(
+        &self,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+        transaction_type: Option<enums::TransactionType>,
+    ) -> Result<responses::TransactionHistoryResponse, CapitalDotComError> {
+        self.check_logged_in()?;
+
         let mut capital_dot_com_endpoints_lock = self
             .capital_dot_com_endpoints
             .lock()
             .unwrap_or_else(|p| p.into_inner());
 
-        let (_, body) = self
-            .runtime
-            .block_on(capital_dot_com_endpoints_lock.get_position(deal_id.to_string()))?;
+        let (_, body) = self.block_on(capital_dot_com_endpoints_lock.get_transaction_history(
+            from,
+            to,
+            transaction_type,
+        ))?;
 
         Ok(body)
     }
 
-    fn close_position(
+    fn realized_pl_between(
         &self,
-        deal_id: &str,
-    ) -> Result<responses::DealReferenceResponse, CapitalDotComError> {
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+        currency: enums::Currency,
+        rates: &HashMap<enums::Currency, f64>,
+    ) -> Result<f64, CapitalDotComError> {
+        let transaction_history =
+            self.get_transaction_history(from, to, Some(enums::TransactionType::Trade))?;
+
+        let mut realized_pl = 0.;
+        for (transaction_currency, profit_and_loss) in transaction_history.realized_pl_by_currency()
+        {
+            if transaction_currency == currency {
+                realized_pl += profit_and_loss;
+                continue;
+            }
+
+            let rate =
+                rates
+                    .get(&transaction_currency)
+                    .ok_or(CapitalDotComError::MissingExchangeRate(
+                        transaction_currency,
+                    ))?;
+            realized_pl += profit_and_loss * rate;
+        }
+
+        Ok(realized_pl)
+    }
+
+    fn get_server_time(&self) -> Result<responses::ServerTimeResponse, CapitalDotComError> {
         let mut capital_dot_com_endpoints_lock = self
             .capital_dot_com_endpoints
             .lock()
             .unwrap_or_else(|p| p.into_inner());
 
-        let (_, body) = self
-            .runtime
-            .block_on(capital_dot_com_endpoints_lock.close_position(deal_id.to_string()))?;
+        let (_, body) = self.block_on(capital_dot_com_endpoints_lock.get_server_time())?;
 
         Ok(body)
     }
 
+    fn server_time(&self) -> Result<chrono::DateTime<chrono::Utc>, CapitalDotComError> {
+        let server_time = self.get_server_time()?;
+
+        server_time
+            .date_time()
+            .ok_or(CapitalDotComError::InvalidTimestamp(
+                server_time.server_time,
+            ))
+    }
+
+    fn get_recent_prices(
+        &self,
+        epic: &str,
+        resolution: enums::Resolution,
+        last_n_bars: usize,
+    ) -> Result<responses::HistoricalPricesResponse, CapitalDotComError> {
+        if last_n_bars > Self::MAX_PRICES_PER_REQUEST {
+            return Err(CapitalDotComError::TooManyParameters);
+        }
+
+        let to = self.server_time()?;
+        // A bit more than needed for gaps in the data
+        let mut window = resolution.duration() * (last_n_bars as i32 * 5 / 4 + 1);
+
+        let mut attempt = 1;
+        loop {
+            self.throttle(Self::REQUEST_INTERVAL)?;
+            match self.get_historical_prices(
+                epic,
+                resolution,
+                Some(Self::MAX_PRICES_PER_REQUEST as i32),
+                to - window,
+                to,
+            ) {
+                Ok(mut prices)
+                    if prices.prices.len() >= last_n_bars
+                        || attempt == Self::RECENT_PRICES_ATTEMPTS =>
+                {
+                    let surplus = prices.prices.len().saturating_sub(last_n_bars);
+                    prices.prices.drain(..surplus);
+
+                    return Ok(prices);
+                }
+                Ok(_) => {}
+                // Windows without prices (e.g. over a weekend) are answered with 404
+                Err(CapitalDotComError::StatusCode(404, _, _))
+                    if attempt < Self::RECENT_PRICES_ATTEMPTS => {}
+                Err(error) => return Err(error),
+            }
+
+            window = window * Self::RECENT_PRICES_WINDOW_GROWTH;
+            attempt += 1;
+        }
+    }
+
     /// * max: The maximum number of the values in answer. Default = 10, max = 1000
     fn get_historical_prices(
         &self,
@@ -277,23 +1823,75 @@ impl traits::CapitalDotComInterface for CapitalDotComAPI {
         from: chrono::DateTime<chrono::Utc>,
         to: chrono::DateTime<chrono::Utc>,
     ) -> Result<responses::HistoricalPricesResponse, CapitalDotComError> {
+        self.check_logged_in()?;
+        let epic = normalize_epic(epic)?;
+
         let mut capital_dot_com_endpoints_lock = self
             .capital_dot_com_endpoints
             .lock()
             .unwrap_or_else(|p| p.into_inner());
 
-        let (_, body) =
-            self.runtime
-                .block_on(capital_dot_com_endpoints_lock.get_historical_prices(
-                    epic.to_string(),
-                    resolution,
-                    max,
-                    from,
-                    to,
-                ))?;
+        let (_, body) = self.block_on(
+            capital_dot_com_endpoints_lock.get_historical_prices(epic, resolution, max, from, to),
+        )?;
 
         Ok(body)
     }
+
+    fn get_historical_prices_paginated(
+        &self,
+        epic: &str,
+        resolution: enums::Resolution,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<responses::Prices>, CapitalDotComError> {
+        let opening_hours = if resolution.duration() < chrono::TimeDelta::days(1) {
+            self.get_market_data(epic)?.instrument.opening_hours
+        } else {
+            None
+        };
+
+        let mut prices: Vec<responses::Prices> = Vec::new();
+        for (window_from, window_to) in
+            Self::price_windows(resolution, from, to, opening_hours.as_ref())
+        {
+            self.throttle(Self::REQUEST_INTERVAL)?;
+            match self.get_historical_prices(
+                epic,
+                resolution,
+                Some(Self::MAX_PRICES_PER_REQUEST as i32),
+                window_from,
+                window_to,
+            ) {
+                Ok(response) => prices.extend(response.prices),
+                // Windows without prices are answered with 404
+                Err(CapitalDotComError::StatusCode(404, _, _)) => {}
+                Err(error) => return Err(error),
+            }
+        }
+        // Neighbouring windows share the candle at their boundary
+        prices.dedup_by(|a, b| a.snapshot_time_UTC == b.snapshot_time_UTC);
+
+        Ok(prices)
+    }
+
+    fn get_candles(
+        &self,
+        epic: &str,
+        resolution: enums::Resolution,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<candle::Candle>, CapitalDotComError> {
+        let prices = self.get_historical_prices_paginated(epic, resolution, from, to)?;
+
+        let mut candles: Vec<candle::Candle> = prices
+            .iter()
+            .filter_map(candle::Candle::from_prices)
+            .collect();
+        candles.sort_by_key(|candle| candle.time);
+
+        Ok(candles)
+    }
 }
 
 #[derive(Debug)]
@@ -304,11 +1902,115 @@ pub enum CapitalDotComError {
     HeaderNotFound,
     FromUtf8Error(FromUtf8Error),
     TooManyParameters,
+    /// 401, the session tokens are invalid or expired. A new session can help.
     Unauthorized,
+    /// 403 with the raw body, e.g. missing permissions or an IP that is not allowlisted.
+    /// A new session does not help.
+    Forbidden(String),
     MissingAuthorization,
     RequestingTooFast(chrono::TimeDelta),
     CurrentAccountNotFound,
+    /// After switch_account the server reports another account (the account id) as active.
+    AccountSwitchNotConfirmed(String),
     NotDifferentAccountId,
+    InvalidDistance(f64),
+    InvalidDealSize(f64),
+    InvalidRiskFraction(f64),
+    WorkingOrderNotFound,
+    Timeout(Duration),
+    InvalidSessionType(String),
+    /// A retry found a matching position (deal_id) that was already opened.
+    PositionAlreadyOpened(String),
+    /// The position still exists after close_position_confirmed.
+    PositionStillOpen(String),
+    /// update_position would not change the position, no request was sent.
+    NoChanges,
+    /// No exchange rate was given for a currency that has to be converted.
+    MissingExchangeRate(enums::Currency),
+    /// A timestamp of the API (milliseconds since the epoch) is out of the range of DateTime.
+    InvalidTimestamp(i64),
+    /// The epic is empty or contains characters epics never have (see normalize_epic).
+    InvalidEpic(String),
+    /// The session does not match the expected session type, see assert_session_type.
+    WrongEnvironment(String),
+    /// The account is in hedging mode, an opposite order would open a new position instead of closing.
+    HedgingModeEnabled,
+    /// The account is not in hedging mode, an opposite position would close the existing one.
+    HedgingModeDisabled,
+    /// Order placement is disabled by set_trading_enabled(false).
+    TradingDisabled,
+    /// The broker does not allow dealing on the active account (dealing_enabled of switch_account).
+    DealingDisabled,
+    /// A trailing stop was requested but the active account does not support trailing stops.
+    TrailingStopsDisabled,
+    /// A call was made before open_session succeeded.
+    NotLoggedIn,
+    /// The login succeeded but the API key has no accounts (e.g. a new or unfunded key).
+    NoAccountsAvailable,
+    /// The login response did not contain the cst and x-security-token headers.
+    LoginFailed,
+    /// The API did not confirm the log out, the session may still be open.
+    LogoutFailed,
+    /// The market does not allow guaranteed stops.
+    GuaranteedStopNotAllowed,
+    /// The guaranteed stop is closer than the min_guaranteed_stop_distance of the market.
+    InvalidGuaranteedStopDistance {
+        distance: f64,
+        min_distance: f64,
+    },
+    /// The parameters of an order can not be combined (e.g. stop_level and stop_distance).
+    ConflictingOrderParameters(String),
+    /// The position was sent but the confirmation could not be fetched.
+    /// The order may have been filled, retry order_confirmation with the deal_reference.
+    ConfirmationFailed {
+        deal_reference: String,
+        error: Box<CapitalDotComError>,
+    },
+    /// The confirmation of the order has the deal status REJECTED or DECLINED.
+    OrderRejected {
+        deal_reference: String,
+        reason: Option<String>,
+    },
+}
+impl CapitalDotComError {
+    /// Errors that might go away when the request is sent again (connection errors, timeouts, 429 and 5xx).
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::ReqwestError(_) | Self::Timeout(_) => true,
+            Self::StatusCode(status, _, _) => *status == 429 || *status >= 500,
+            _ => false,
+        }
+    }
+
+    /// Errors that a new session (open_session) can fix, only 401.
+    pub fn needs_reauthentication(&self) -> bool {
+        matches!(self, Self::Unauthorized)
+    }
+
+    /// HTTP status code if the API returned an error status.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Self::StatusCode(status, _, _) => Some(*status),
+            Self::Forbidden(_) => Some(403),
+            _ => None,
+        }
+    }
+
+    /// Parsed error body if the API returned an error status.
+    pub fn api_error(&self) -> Option<&responses::APIError> {
+        match self {
+            Self::StatusCode(_, api_error, _) => Some(api_error),
+            _ => None,
+        }
+    }
+
+    /// Raw error body if the API returned an error status.
+    pub fn raw_body(&self) -> Option<&str> {
+        match self {
+            Self::StatusCode(_, _, raw_body) | Self::Forbidden(raw_body) => Some(raw_body),
+            _ => None,
+        }
+    }
 }
 impl Display for CapitalDotComError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -332,6 +2034,8 @@ mod tests {
         pub test_account_name: String,
     }
 
+    fn _assert_send_sync<T: Send + Sync>() {}
+
     fn get_api_credentials() -> Credentials {
         let credentials_file = fs::File::open("test_api_credentials.json").unwrap();
         let credentials: Credentials = serde_json::from_reader(credentials_file).unwrap();
@@ -339,6 +2043,112 @@ mod tests {
         credentials
     }
 
+    #[test]
+    fn api_is_send_sync() {
+        _assert_send_sync::<CapitalDotComAPI>();
+    }
+
+    #[test]
+    fn normalize_epics() {
+        assert_eq!(normalize_epic(" EURUSD\n").unwrap(), "EURUSD");
+        assert_eq!(normalize_epic("OIL_CRUDE").unwrap(), "OIL_CRUDE");
+        assert_eq!(normalize_epic("Rds.A-L").unwrap(), "Rds.A-L");
+
+        for epic in ["", "   ", "EUR USD", "TSLA/US", "AAPL?"] {
+            assert!(matches!(
+                normalize_epic(epic),
+                Err(CapitalDotComError::InvalidEpic(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn status_code_accessors() {
+        let raw_body = r#"{"errorCode": "error.invalid.details"}"#;
+        let error = CapitalDotComError::StatusCode(
+            400,
+            serde_json::from_str(raw_body).unwrap(),
+            raw_body.to_string(),
+        );
+
+        assert_eq!(error.status(), Some(400));
+        assert_eq!(
+            error.api_error().unwrap().error_code,
+            "error.invalid.details"
+        );
+        assert_eq!(error.raw_body(), Some(raw_body));
+
+        let error = CapitalDotComError::Unauthorized;
+        assert_eq!(error.status(), None);
+        assert!(error.api_error().is_none());
+        assert_eq!(error.raw_body(), None);
+    }
+
+    #[test]
+    fn transient_errors() {
+        let status_code = |status| {
+            CapitalDotComError::StatusCode(
+                status,
+                serde_json::from_str(r#"{"errorCode": "error"}"#).unwrap(),
+                String::new(),
+            )
+        };
+
+        assert!(status_code(429).is_transient());
+        assert!(status_code(503).is_transient());
+        assert!(!status_code(400).is_transient());
+        assert!(CapitalDotComError::Timeout(Duration::from_secs(1)).is_transient());
+        assert!(!CapitalDotComError::Unauthorized.is_transient());
+
+        let retry_policy = RetryPolicy::default();
+        assert_eq!(retry_policy.order.retries, 0);
+        assert!(retry_policy.read.retries > 0);
+    }
+
+    #[test]
+    fn not_logged_in() {
+        let mut capital_api = CapitalDotComAPI::new(
+            SessionType::Demo,
+            String::new(),
+            String::new(),
+            String::new(),
+        );
+
+        assert!(capital_api.streaming_credentials().is_none());
+        assert!(matches!(
+            capital_api.switch_account("0001"),
+            Err(CapitalDotComError::NotLoggedIn)
+        ));
+        assert!(matches!(
+            capital_api.get_all_positions(),
+            Err(CapitalDotComError::NotLoggedIn)
+        ));
+        assert!(matches!(
+            capital_api.get_market_data("TSLA"),
+            Err(CapitalDotComError::NotLoggedIn)
+        ));
+    }
+
+    #[test]
+    fn block_on_timeout() {
+        let mut capital_api = CapitalDotComAPI::new(
+            SessionType::Demo,
+            String::new(),
+            String::new(),
+            String::new(),
+        );
+        capital_api.set_request_timeout(Duration::from_millis(10));
+
+        let result = capital_api.block_on(async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok(())
+        });
+        assert!(matches!(result, Err(CapitalDotComError::Timeout(_))));
+
+        let result = capital_api.block_on(async { Ok(1) });
+        assert!(matches!(result, Ok(1)));
+    }
+
     #[test]
     fn full_test() {
         println!("\n\n\n");
@@ -352,6 +2162,7 @@ mod tests {
         );
 
         let session_details = capital_api.open_session().unwrap();
+        capital_api.assert_session_type(SessionType::Demo).unwrap();
 
         let all_accounts = capital_api.get_all_accounts().unwrap();
         println!("{:?}", all_accounts);
@@ -373,7 +2184,9 @@ mod tests {
             balance.balance, balance.deposit, balance.profit_loss, balance.available
         );
 
-        let markets = capital_api.search_market("Tesla", Vec::new()).unwrap();
+        let markets = capital_api
+            .search_market(Some("Tesla"), Vec::new())
+            .unwrap();
         let mut epic = String::new();
         for market in markets.markets {
             if market.instrument_name.contains("Tesla") {
@@ -390,7 +2203,8 @@ mod tests {
             &epic,
             market.dealing_rules.min_deal_size.value * 10.,
         )
-        .build();
+        .build()
+        .unwrap();
         let deal_reference = capital_api.open_position(position_data).unwrap();
         println!("Order: {:?}\n\n", deal_reference);
 
@@ -1,6 +1,8 @@
 use serde::Serialize;
 
 use crate::enums;
+use crate::responses;
+use crate::CapitalDotComError;
 
 #[derive(Debug, Serialize)]
 pub struct CreateSessionBody {
@@ -16,34 +18,42 @@ impl CreateSessionBody {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreatePositionBody {
     direction: enums::Direction, // Long or Short position.
     epic: String,                // Instrument epic identifier.
-    size: f32,
+    size: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
     guaranteed_stop: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     trailing_stop: Option<bool>,
-    stop_level: Option<f32>,
-    stop_distance: Option<f32>,
-    stop_amount: Option<f32>,
-    profit_level: Option<f32>,
-    profit_distance: Option<f32>,
-    profit_amount: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_level: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_distance: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_amount: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    profit_level: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    profit_distance: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    profit_amount: Option<f64>,
 }
 impl CreatePositionBody {
     pub fn new(
         direction: enums::Direction,
         epic: &str,
-        size: f32,
+        size: f64,
         guaranteed_stop: Option<bool>,
         trailing_stop: Option<bool>,
-        stop_level: Option<f32>,
-        stop_distance: Option<f32>,
-        stop_amount: Option<f32>,
-        profit_level: Option<f32>,
-        profit_distance: Option<f32>,
-        profit_amount: Option<f32>,
+        stop_level: Option<f64>,
+        stop_distance: Option<f64>,
+        stop_amount: Option<f64>,
+        profit_level: Option<f64>,
+        profit_distance: Option<f64>,
+        profit_amount: Option<f64>,
     ) -> Self {
         Self {
             direction,
@@ -59,29 +69,58 @@ impl CreatePositionBody {
             profit_amount,
         }
     }
-}
-impl Clone for CreatePositionBody {
-    fn clone(&self) -> Self {
-        Self {
-            direction: self.direction.clone(),
-            epic: self.epic.clone(),
-            size: self.size.clone(),
-            guaranteed_stop: self.guaranteed_stop.clone(),
-            trailing_stop: self.trailing_stop.clone(),
-            stop_level: self.stop_level.clone(),
-            stop_distance: self.stop_distance.clone(),
-            stop_amount: self.stop_amount.clone(),
-            profit_level: self.profit_level.clone(),
-            profit_distance: self.profit_distance.clone(),
-            profit_amount: self.profit_amount.clone(),
+
+    /// Check a guaranteed stop against the market: the market has to allow guaranteed stops
+    /// and the stop_distance has to be at least min_guaranteed_stop_distance,
+    /// a limit given as a percentage at the current opening price (see Snapshot::opening_price).
+    /// Guaranteed stops set by stop_level or stop_amount are only checked for the market.
+    pub fn check_guaranteed_stop(
+        &self,
+        market: &responses::SingleMarketDetailsResponse,
+    ) -> Result<(), CapitalDotComError> {
+        if self.guaranteed_stop != Some(true) {
+            return Ok(());
+        }
+
+        if !market.guaranteed_stops_allowed() {
+            return Err(CapitalDotComError::GuaranteedStopNotAllowed);
+        }
+
+        let price = market.snapshot.opening_price(self.direction);
+        match self.stop_distance {
+            Some(stop_distance)
+                if !market
+                    .dealing_rules
+                    .is_valid_guaranteed_stop_distance_at(stop_distance, price) =>
+            {
+                Err(CapitalDotComError::InvalidGuaranteedStopDistance {
+                    distance: stop_distance,
+                    min_distance: market
+                        .dealing_rules
+                        .min_guaranteed_stop_distance_at(price)
+                        .unwrap_or(0.),
+                })
+            }
+            _ => Ok(()),
         }
     }
+
+    pub fn has_trailing_stop(&self) -> bool {
+        self.trailing_stop == Some(true)
+    }
+
+    /// Check if the position has the same epic, direction and size.
+    pub fn matches(&self, position: &responses::PositionResponse) -> bool {
+        position.market.epic == self.epic
+            && position.position.direction == self.direction
+            && position.position.size == self.size
+    }
 }
 pub struct CreatePositionBodyBuilder {
     create_position_body: CreatePositionBody,
 }
 impl CreatePositionBodyBuilder {
-    pub fn new(direction: enums::Direction, epic: &str, size: f32) -> Self {
+    pub fn new(direction: enums::Direction, epic: &str, size: f64) -> Self {
         Self {
             create_position_body: CreatePositionBody::new(
                 direction, epic, size, None, None, None, None, None, None, None, None,
@@ -90,6 +129,7 @@ impl CreatePositionBodyBuilder {
     }
 
     /// Needs stop_level, stop_distance or stop_amount set. Disables trailing_stop. Can not be set if hedging mode is enabled.
+    /// Guaranteed stops cost a premium and need a minimum distance, see CreatePositionBody::check_guaranteed_stop.
     pub fn guaranteed_stop(mut self, guaranteed_stop: bool) -> Self {
         self.create_position_body.guaranteed_stop = Some(guaranteed_stop);
         self.create_position_body.trailing_stop = None;
@@ -98,6 +138,7 @@ impl CreatePositionBodyBuilder {
     }
 
     /// Needs to have stop_distance set. If disabled stop_distance gets disabled. Disables guaranteed_stop.
+    /// Only accepted if the account has trailing stops enabled, see CapitalDotComAPI::trailing_stops_enabled.
     pub fn trailing_stop(mut self, trailing_stop: bool) -> Self {
         if !trailing_stop {
             self.create_position_body.stop_distance = None;
@@ -110,87 +151,343 @@ impl CreatePositionBodyBuilder {
     }
 
     /// Price level when a stop loss will be triggered.
-    pub fn stop_level(mut self, stop_level: f32) -> Self {
+    pub fn stop_level(mut self, stop_level: f64) -> Self {
         self.create_position_body.stop_level = Some(stop_level);
 
         self
     }
 
     /// Distance between current and stop loss triggering price.
-    pub fn stop_distance(mut self, stop_distance: f32) -> Self {
+    pub fn stop_distance(mut self, stop_distance: f64) -> Self {
         self.create_position_body.stop_distance = Some(stop_distance);
 
         self
     }
 
     /// Loss amount when a stop loss will be triggered.
-    pub fn stop_amount(mut self, stop_amount: f32) -> Self {
+    pub fn stop_amount(mut self, stop_amount: f64) -> Self {
         self.create_position_body.stop_amount = Some(stop_amount);
 
         self
     }
 
     /// Price level when a take profit will be triggered.
-    pub fn profit_level(mut self, profit_level: f32) -> Self {
+    pub fn profit_level(mut self, profit_level: f64) -> Self {
         self.create_position_body.profit_level = Some(profit_level);
 
         self
     }
 
     /// Distance between current and take profit triggering price.
-    pub fn profit_distance(mut self, profit_distance: f32) -> Self {
+    pub fn profit_distance(mut self, profit_distance: f64) -> Self {
         self.create_position_body.profit_distance = Some(profit_distance);
 
         self
     }
 
     /// Profit amount when a take profit will be triggered
-    pub fn profit_amount(mut self, profit_amount: f32) -> Self {
+    pub fn profit_amount(mut self, profit_amount: f64) -> Self {
         self.create_position_body.profit_amount = Some(profit_amount);
 
         self
     }
 
-    pub fn build(self) -> CreatePositionBody {
-        self.create_position_body
+    /// stop_level percent away from current_price on the losing side (below for BUY, above for SELL).
+    /// The distance is checked against the dealing rules, see DealingRules::is_valid_stop_or_profit_distance_at.
+    pub fn stop_at_percent(
+        self,
+        current_price: f64,
+        percent: f64,
+        dealing_rules: &responses::DealingRules,
+    ) -> Result<Self, CapitalDotComError> {
+        let distance = Self::percent_distance(current_price, percent, dealing_rules)?;
+        let stop_level = match self.create_position_body.direction {
+            enums::Direction::BUY => current_price - distance,
+            enums::Direction::SELL => current_price + distance,
+        };
+
+        Ok(self.stop_level(stop_level))
+    }
+
+    /// profit_level percent away from current_price on the winning side (above for BUY, below for SELL).
+    /// The distance is checked against the dealing rules, see DealingRules::is_valid_stop_or_profit_distance_at.
+    pub fn profit_at_percent(
+        self,
+        current_price: f64,
+        percent: f64,
+        dealing_rules: &responses::DealingRules,
+    ) -> Result<Self, CapitalDotComError> {
+        let distance = Self::percent_distance(current_price, percent, dealing_rules)?;
+        let profit_level = match self.create_position_body.direction {
+            enums::Direction::BUY => current_price + distance,
+            enums::Direction::SELL => current_price - distance,
+        };
+
+        Ok(self.profit_level(profit_level))
+    }
+
+    fn percent_distance(
+        current_price: f64,
+        percent: f64,
+        dealing_rules: &responses::DealingRules,
+    ) -> Result<f64, CapitalDotComError> {
+        let distance = current_price * percent / 100.;
+        if !dealing_rules.is_valid_stop_or_profit_distance_at(distance, current_price) {
+            return Err(CapitalDotComError::InvalidDistance(distance));
+        }
+
+        Ok(distance)
+    }
+
+    /// Checks that the stop and the profit are each set by only one of level, distance and amount
+    /// and that a trailing stop has a stop_distance. The epic gets normalized (see normalize_epic).
+    pub fn build(mut self) -> Result<CreatePositionBody, CapitalDotComError> {
+        self.create_position_body.epic = crate::normalize_epic(&self.create_position_body.epic)?;
+        let body = &self.create_position_body;
+
+        let stops = [
+            ("stop_level", body.stop_level),
+            ("stop_distance", body.stop_distance),
+            ("stop_amount", body.stop_amount),
+        ];
+        let profits = [
+            ("profit_level", body.profit_level),
+            ("profit_distance", body.profit_distance),
+            ("profit_amount", body.profit_amount),
+        ];
+        check_only_one_set(stops)?;
+        check_only_one_set(profits)?;
+
+        if body.trailing_stop == Some(true) && body.stop_distance.is_none() {
+            return Err(CapitalDotComError::ConflictingOrderParameters(
+                String::from("trailing_stop needs stop_distance"),
+            ));
+        }
+
+        Ok(self.create_position_body)
     }
 }
 
-#[derive(Debug, Serialize)]
+/// Fails with ConflictingOrderParameters if more than one of the values is set.
+fn check_only_one_set(values: [(&str, Option<f64>); 3]) -> Result<(), CapitalDotComError> {
+    let set: Vec<&str> = values
+        .iter()
+        .filter(|(_, value)| value.is_some())
+        .map(|(name, _)| *name)
+        .collect();
+
+    if set.len() > 1 {
+        return Err(CapitalDotComError::ConflictingOrderParameters(format!(
+            "only one of {} can be set",
+            set.join(", ")
+        )));
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
 #[serde(rename_all = "camelCase")]
 /// # ***Field explanation:***
 /// NAME                    DESCRIPTION
 /// guaranteedStop          Must be true if a guaranteed stop is required.
+///
+/// Fields that are not set are not sent and keep their current value, see PositionUpdateBodyBuilder.
 pub struct PositionUpdateBody {
-    guaranteed_stop: bool,
-    trailing_stop: bool,
-    stop_level: f32,
-    stop_distance: f32,
-    stop_amount: f32,
-    profit_level: f32,
-    profit_distance: f32,
-    profit_amount: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    guaranteed_stop: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trailing_stop: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_level: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_distance: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_amount: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    profit_level: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    profit_distance: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    profit_amount: Option<f64>,
 }
 impl PositionUpdateBody {
     pub fn new(
         guaranteed_stop: bool,
         trailing_stop: bool,
-        stop_level: f32,
-        stop_distance: f32,
-        stop_amount: f32,
-        profit_level: f32,
-        profit_distance: f32,
-        profit_amount: f32,
+        stop_level: f64,
+        stop_distance: f64,
+        stop_amount: f64,
+        profit_level: f64,
+        profit_distance: f64,
+        profit_amount: f64,
     ) -> Self {
         Self {
-            guaranteed_stop,
-            trailing_stop,
-            stop_level,
-            stop_distance,
-            stop_amount,
-            profit_level,
-            profit_distance,
-            profit_amount,
+            guaranteed_stop: Some(guaranteed_stop),
+            trailing_stop: Some(trailing_stop),
+            stop_level: Some(stop_level),
+            stop_distance: Some(stop_distance),
+            stop_amount: Some(stop_amount),
+            profit_level: Some(profit_level),
+            profit_distance: Some(profit_distance),
+            profit_amount: Some(profit_amount),
+        }
+    }
+
+    pub fn has_trailing_stop(&self) -> bool {
+        self.trailing_stop == Some(true)
+    }
+
+    /// Check if the update would change the position.
+    /// Levels and guaranteed_stop are compared with the position, fields the position does not
+    /// report (trailing_stop, distances and amounts) count as a change whenever they are set.
+    pub fn changes(&self, position: &responses::PositionData) -> bool {
+        let level_changes =
+            |new: Option<f64>, current: Option<f64>| new.is_some_and(|new| Some(new) != current);
+
+        self.guaranteed_stop
+            .is_some_and(|guaranteed_stop| guaranteed_stop != position.guaranteed_stop)
+            || level_changes(self.stop_level, position.stop_level)
+            || level_changes(self.profit_level, position.profit_level)
+            || self.trailing_stop.is_some()
+            || self.stop_distance.is_some()
+            || self.stop_amount.is_some()
+            || self.profit_distance.is_some()
+            || self.profit_amount.is_some()
+    }
+}
+#[derive(Default)]
+pub struct PositionUpdateBodyBuilder {
+    position_update_body: PositionUpdateBody,
+}
+impl PositionUpdateBodyBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Needs a stop. Can not be set if hedging mode is enabled.
+    pub fn guaranteed_stop(mut self, guaranteed_stop: bool) -> Self {
+        self.position_update_body.guaranteed_stop = Some(guaranteed_stop);
+
+        self
+    }
+
+    /// Needs to have stop_distance set.
+    pub fn trailing_stop(mut self, trailing_stop: bool) -> Self {
+        self.position_update_body.trailing_stop = Some(trailing_stop);
+
+        self
+    }
+
+    /// Price level when a stop loss will be triggered.
+    pub fn stop_level(mut self, stop_level: f64) -> Self {
+        self.position_update_body.stop_level = Some(stop_level);
+
+        self
+    }
+
+    /// Distance between current and stop loss triggering price.
+    pub fn stop_distance(mut self, stop_distance: f64) -> Self {
+        self.position_update_body.stop_distance = Some(stop_distance);
+
+        self
+    }
+
+    /// Loss amount when a stop loss will be triggered.
+    pub fn stop_amount(mut self, stop_amount: f64) -> Self {
+        self.position_update_body.stop_amount = Some(stop_amount);
+
+        self
+    }
+
+    /// Price level when a take profit will be triggered.
+    pub fn profit_level(mut self, profit_level: f64) -> Self {
+        self.position_update_body.profit_level = Some(profit_level);
+
+        self
+    }
+
+    /// Distance between current and take profit triggering price.
+    pub fn profit_distance(mut self, profit_distance: f64) -> Self {
+        self.position_update_body.profit_distance = Some(profit_distance);
+
+        self
+    }
+
+    /// Profit amount when a take profit will be triggered
+    pub fn profit_amount(mut self, profit_amount: f64) -> Self {
+        self.position_update_body.profit_amount = Some(profit_amount);
+
+        self
+    }
+
+    /// Checks that the stop and the profit are each set by only one of level, distance and amount.
+    pub fn build(self) -> Result<PositionUpdateBody, CapitalDotComError> {
+        let body = &self.position_update_body;
+
+        check_only_one_set([
+            ("stop_level", body.stop_level),
+            ("stop_distance", body.stop_distance),
+            ("stop_amount", body.stop_amount),
+        ])?;
+        check_only_one_set([
+            ("profit_level", body.profit_level),
+            ("profit_distance", body.profit_distance),
+            ("profit_amount", body.profit_amount),
+        ])?;
+
+        Ok(self.position_update_body)
+    }
+}
+
+/// How to close a position with an order in the opposite direction.
+///  * order_type: None for a market order, LIMIT or STOP for a working order at level
+///  * level: required for LIMIT and STOP
+///  * size: None for the full size of the position, less for a partial close
+#[derive(Debug, Clone)]
+pub struct ClosePositionBody {
+    pub(crate) order_type: Option<enums::OrderType>,
+    pub(crate) level: Option<f64>,
+    pub(crate) size: Option<f64>,
+}
+impl ClosePositionBody {
+    pub fn new(
+        order_type: Option<enums::OrderType>,
+        level: Option<f64>,
+        size: Option<f64>,
+    ) -> Self {
+        Self {
+            order_type,
+            level,
+            size,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateWorkingOrderBody {
+    direction: enums::Direction,
+    epic: String,
+    size: f64,
+    level: f64,
+    #[serde(rename = "type")]
+    order_type: enums::OrderType,
+}
+impl CreateWorkingOrderBody {
+    pub fn new(
+        direction: enums::Direction,
+        epic: &str,
+        size: f64,
+        level: f64,
+        order_type: enums::OrderType,
+    ) -> Self {
+        Self {
+            direction,
+            epic: epic.to_string(),
+            size,
+            level,
+            order_type,
         }
     }
 }
@@ -205,3 +502,220 @@ impl SwitchActiveAccountBody {
         Self { account_id }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conflict(builder: CreatePositionBodyBuilder) -> String {
+        match builder.build() {
+            Err(CapitalDotComError::ConflictingOrderParameters(conflict)) => conflict,
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn build_validation() {
+        let builder = || CreatePositionBodyBuilder::new(enums::Direction::BUY, "TSLA", 1.);
+
+        assert!(builder()
+            .stop_level(160.)
+            .profit_distance(10.)
+            .build()
+            .is_ok());
+        assert_eq!(
+            conflict(builder().stop_level(160.).stop_distance(10.)),
+            "only one of stop_level, stop_distance can be set"
+        );
+        assert_eq!(
+            conflict(builder().profit_distance(10.).profit_amount(100.)),
+            "only one of profit_distance, profit_amount can be set"
+        );
+        assert_eq!(
+            conflict(builder().trailing_stop(true)),
+            "trailing_stop needs stop_distance"
+        );
+        assert!(builder()
+            .stop_distance(10.)
+            .trailing_stop(true)
+            .build()
+            .is_ok());
+    }
+
+    #[test]
+    fn levels_at_percent() {
+        let rules = responses::DealingRules {
+            min_step_distance: responses::UnitValue {
+                unit: enums::Unit::POINTS,
+                value: 0.01,
+            },
+            min_deal_size: responses::UnitValue {
+                unit: enums::Unit::POINTS,
+                value: 1.,
+            },
+            max_deal_size: responses::UnitValue {
+                unit: enums::Unit::POINTS,
+                value: 1000.,
+            },
+            min_size_increment: responses::UnitValue {
+                unit: enums::Unit::POINTS,
+                value: 1.,
+            },
+            min_guaranteed_stop_distance: Some(responses::UnitValue {
+                unit: enums::Unit::PERCENTAGE,
+                value: 5.,
+            }),
+            min_stop_or_profit_distance: responses::UnitValue {
+                unit: enums::Unit::PERCENTAGE,
+                value: 0.5,
+            },
+            max_stop_or_profit_distance: responses::UnitValue {
+                unit: enums::Unit::PERCENTAGE,
+                value: 50.,
+            },
+            market_order_preference: String::from("AVAILABLE_DEFAULT_ON"),
+            trailing_stops_preference: String::from("AVAILABLE"),
+        };
+
+        let long = CreatePositionBodyBuilder::new(enums::Direction::BUY, "TSLA", 1.)
+            .stop_at_percent(200., 1., &rules)
+            .unwrap()
+            .profit_at_percent(200., 2., &rules)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(long.stop_level, Some(198.));
+        assert_eq!(long.profit_level, Some(204.));
+
+        let short = CreatePositionBodyBuilder::new(enums::Direction::SELL, "TSLA", 1.)
+            .stop_at_percent(200., 1., &rules)
+            .unwrap()
+            .profit_at_percent(200., 2., &rules)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(short.stop_level, Some(202.));
+        assert_eq!(short.profit_level, Some(196.));
+
+        let builder = || CreatePositionBodyBuilder::new(enums::Direction::BUY, "TSLA", 1.);
+        assert!(matches!(
+            builder().stop_at_percent(200., 0.1, &rules),
+            Err(CapitalDotComError::InvalidDistance(_))
+        ));
+        assert!(matches!(
+            builder().profit_at_percent(200., 60., &rules),
+            Err(CapitalDotComError::InvalidDistance(_))
+        ));
+    }
+
+    fn json<T: Serialize>(body: &T) -> String {
+        serde_json::to_string(body).unwrap()
+    }
+
+    #[test]
+    fn serialized_bodies() {
+        let required_only = CreatePositionBodyBuilder::new(enums::Direction::BUY, "TSLA", 1.5)
+            .build()
+            .unwrap();
+        assert_eq!(
+            json(&required_only),
+            r#"{"direction":"BUY","epic":"TSLA","size":1.5}"#
+        );
+
+        let all_options = CreatePositionBody::new(
+            enums::Direction::SELL,
+            "TSLA",
+            2.,
+            Some(false),
+            Some(true),
+            Some(190.),
+            Some(10.),
+            Some(50.),
+            Some(150.),
+            Some(20.),
+            Some(100.),
+        );
+        assert_eq!(
+            json(&all_options),
+            concat!(
+                r#"{"direction":"SELL","epic":"TSLA","size":2.0,"guaranteedStop":false,"trailingStop":true,"#,
+                r#""stopLevel":190.0,"stopDistance":10.0,"stopAmount":50.0,"#,
+                r#""profitLevel":150.0,"profitDistance":20.0,"profitAmount":100.0}"#
+            )
+        );
+
+        assert_eq!(json(&PositionUpdateBody::default()), "{}");
+        assert_eq!(
+            json(&PositionUpdateBody::new(
+                true, false, 165., 5., 50., 180., 10., 100.
+            )),
+            concat!(
+                r#"{"guaranteedStop":true,"trailingStop":false,"stopLevel":165.0,"stopDistance":5.0,"#,
+                r#""stopAmount":50.0,"profitLevel":180.0,"profitDistance":10.0,"profitAmount":100.0}"#
+            )
+        );
+
+        assert_eq!(
+            json(&CreateWorkingOrderBody::new(
+                enums::Direction::BUY,
+                "TSLA",
+                1.,
+                160.,
+                enums::OrderType::LIMIT
+            )),
+            r#"{"direction":"BUY","epic":"TSLA","size":1.0,"level":160.0,"type":"LIMIT"}"#
+        );
+        assert_eq!(
+            json(&SwitchActiveAccountBody::new(String::from("0002"))),
+            r#"{"accountId":"0002"}"#
+        );
+        assert_eq!(
+            json(&CreateSessionBody::new("user@example.com", "password")),
+            r#"{"identifier":"user@example.com","password":"password"}"#
+        );
+    }
+
+    #[test]
+    fn position_update_changes() {
+        let position: responses::PositionData = serde_json::from_str(
+            r#"{
+                "contractSize": 1,
+                "createdDate": "2024-06-11T15:00:00.000",
+                "createdDateUTC": "2024-06-11T13:00:00.000",
+                "dealId": "0001",
+                "dealReference": "p_0001",
+                "size": 1,
+                "leverage": 5,
+                "upl": 0.0,
+                "direction": "BUY",
+                "level": 170.0,
+                "currency": "USD",
+                "stopLevel": 165.0
+            }"#,
+        )
+        .unwrap();
+        let update = |builder: PositionUpdateBodyBuilder| builder.build().unwrap();
+
+        assert!(!update(PositionUpdateBodyBuilder::new()).changes(&position));
+        assert!(!update(PositionUpdateBodyBuilder::new().stop_level(165.)).changes(&position));
+        assert!(
+            !update(PositionUpdateBodyBuilder::new().guaranteed_stop(false)).changes(&position)
+        );
+        assert!(update(PositionUpdateBodyBuilder::new().stop_level(166.)).changes(&position));
+        assert!(update(PositionUpdateBodyBuilder::new().profit_level(180.)).changes(&position));
+        assert!(update(PositionUpdateBodyBuilder::new().stop_distance(5.)).changes(&position));
+
+        assert!(matches!(
+            PositionUpdateBodyBuilder::new()
+                .stop_level(165.)
+                .stop_amount(50.)
+                .build(),
+            Err(CapitalDotComError::ConflictingOrderParameters(_))
+        ));
+        assert_eq!(
+            serde_json::to_string(&update(PositionUpdateBodyBuilder::new().stop_level(166.)))
+                .unwrap(),
+            r#"{"stopLevel":166.0}"#
+        );
+    }
+}
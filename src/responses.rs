@@ -1,13 +1,84 @@
-use serde::Deserialize;
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
+use serde::{Deserialize, Deserializer};
 
 use crate::enums;
+use crate::request_bodies;
 
 type Timestamp = i64;
 
+fn default_contract_size() -> i64 {
+    1
+}
+
+/// Value of a move of one point (price unit) for a size of 1: contract_size / scaling_factor
+fn value_per_point(contract_size: f64, scaling_factor: f64) -> f64 {
+    contract_size / scaling_factor
+}
+
+/// Accept an integer written as a float as well (e.g. 200 and 200.0).
+fn deserialize_whole_number<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: TryFrom<i64>,
+{
+    let number = f64::deserialize(deserializer)?;
+    if number.fract() != 0. || number.abs() >= i64::MAX as f64 {
+        return Err(serde::de::Error::custom(format!(
+            "{} is not a whole number",
+            number
+        )));
+    }
+
+    T::try_from(number as i64)
+        .map_err(|_| serde::de::Error::custom(format!("{} is out of range", number)))
+}
+
+/// Accept a number as well as a number inside of a string (e.g. 10.0 and "10.0").
+fn deserialize_number_from_string<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr + Deserialize<'de>,
+    T::Err: Display,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrString<T> {
+        Number(T),
+        String(String),
+    }
+
+    match NumberOrString::<T>::deserialize(deserializer)? {
+        NumberOrString::Number(number) => Ok(number),
+        NumberOrString::String(string) => string.trim().parse().map_err(serde::de::Error::custom),
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct APIError {
     pub error_code: String,
+    /// Field level validation errors if the API returned any.
+    #[serde(default)]
+    pub errors: Option<Vec<FieldError>>,
+}
+impl APIError {
+    /// Error for a body that is not an error of the API (e.g. the HTML page of a proxy), the error_code is the raw body.
+    pub fn from_raw_body(body: &str) -> Self {
+        Self {
+            error_code: body.to_string(),
+            errors: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldError {
+    pub field: Option<String>,
+    pub error_code: Option<String>,
+    pub message: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -15,6 +86,12 @@ pub struct APIError {
 pub struct ServerTimeResponse {
     pub server_time: Timestamp,
 }
+impl ServerTimeResponse {
+    /// server_time (milliseconds since the epoch) as a DateTime
+    pub fn date_time(&self) -> Option<DateTime<Utc>> {
+        DateTime::from_timestamp_millis(self.server_time)
+    }
+}
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -38,6 +115,7 @@ pub struct CreateNewSessionResponse {
     pub currency_symbol: char,
     pub current_account_id: String,
     pub streaming_host: String,
+    #[serde(default)]
     pub accounts: Vec<Account>,
     pub client_id: String,
     pub timezone_offset: i8,
@@ -45,14 +123,20 @@ pub struct CreateNewSessionResponse {
     pub has_active_live_accounts: bool,
     pub trailing_stops_enabled: bool,
 }
+impl CreateNewSessionResponse {
+    /// Time zone of the times without UTC suffix (e.g. update_time)
+    pub fn timezone(&self) -> FixedOffset {
+        timezone_from_offset(self.timezone_offset)
+    }
+}
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BalanceAccountInfo {
-    pub balance: f32,
-    pub deposit: f32,
-    pub profit_loss: f32,
-    pub available: f32,
+    pub balance: f64,
+    pub deposit: f64,
+    pub profit_loss: f64,
+    pub available: f64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -77,6 +161,28 @@ pub struct SessionDetailsResponse {
     pub currency: enums::Currency,
     pub stream_endpoint: String,
 }
+impl SessionDetailsResponse {
+    /// Time zone of the times without UTC suffix (e.g. update_time)
+    pub fn timezone(&self) -> FixedOffset {
+        timezone_from_offset(self.timezone_offset)
+    }
+}
+
+/// Offset of whole hours as FixedOffset, UTC if the offset is out of range.
+pub(crate) fn timezone_from_offset(timezone_offset: i8) -> FixedOffset {
+    FixedOffset::east_opt(timezone_offset as i32 * 3600)
+        .unwrap_or(FixedOffset::east_opt(0).unwrap())
+}
+
+/// Parse a time in the local time of the session (fields without UTC suffix) into UTC.
+/// The timezone is CapitalDotComAPI::session_timezone or the timezone of the session responses.
+pub fn parse_local_time(time: &str, timezone: FixedOffset) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S%.f")
+        .ok()?
+        .and_local_timezone(timezone)
+        .single()
+        .map(|time| time.to_utc())
+}
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -87,10 +193,25 @@ pub struct SwitchAccountResponse {
     pub has_active_live_accounts: bool,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountPreferencesResponse {
+    pub hedging_mode: bool,
+    /// Leverage per instrument group (e.g. "SHARES", "CURRENCIES")
+    pub leverages: HashMap<String, Leverage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Leverage {
+    pub current: i32,
+    pub available: Vec<i32>,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SessionLogOutResponse {
-    pub status: String,
+    pub status: LogoutStatus,
 }
 
 #[derive(Debug, Deserialize)]
@@ -98,6 +219,20 @@ pub struct SessionLogOutResponse {
 pub struct AllAccountsResponse {
     pub accounts: Vec<StatusAccount>,
 }
+impl AllAccountsResponse {
+    /// Distinct currencies of all accounts, sorted.
+    pub fn currencies(&self) -> Vec<enums::Currency> {
+        let mut currencies: Vec<enums::Currency> = self
+            .accounts
+            .iter()
+            .map(|account| account.currency)
+            .collect();
+        currencies.sort();
+        currencies.dedup();
+
+        currencies
+    }
+}
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -116,105 +251,523 @@ pub struct StatusAccount {
 #[serde(rename_all = "camelCase")]
 pub struct OrderConfirmationResponse {
     pub date: String,
+    /// Missing in some rejected confirmations
+    #[serde(default)]
     pub status: Status,
     pub deal_status: DealStatus,
+    /// Why the order was rejected, only set if deal_status is REJECTED
+    pub reject_reason: Option<String>,
     pub epic: String,
     pub deal_reference: String,
     pub deal_id: String,
     pub affected_deals: Vec<AffectedDeal>,
-    pub level: f32,
-    pub size: f32,
+    pub level: f64,
+    pub size: f64,
     pub direction: enums::Direction,
     pub guaranteed_stop: bool,
     pub trailing_stop: bool,
 }
 
+impl OrderConfirmationResponse {
+    /// The order is not processed yet, the confirmation will change.
+    pub fn is_pending(&self) -> bool {
+        matches!(self.status, Status::PENDING)
+    }
+
+    /// The order was not executed.
+    pub fn is_rejected(&self) -> bool {
+        matches!(
+            self.deal_status,
+            DealStatus::REJECTED | DealStatus::DECLINED
+        )
+    }
+
+    /// deal_id of the opened position: the first opened affected deal, deal_id if there is none.
+    /// The deal_id of the confirmation can differ from the deal_id of the position it opened.
+    pub fn position_deal_id(&self) -> &str {
+        self.affected_deals
+            .iter()
+            .find(|deal| matches!(deal.status, Status::OPEN | Status::OPENED))
+            .map_or(&self.deal_id, |deal| &deal.deal_id)
+    }
+
+    /// Affected deals that were filled (status OPEN or OPENED) with their size and level.
+    /// If no affected deal carries a size, the size and level of an accepted confirmation are used.
+    fn fills(&self) -> Vec<(f64, f64)> {
+        let fills: Vec<(f64, f64)> = self
+            .affected_deals
+            .iter()
+            .filter(|deal| matches!(deal.status, Status::OPEN | Status::OPENED))
+            .filter_map(|deal| Some((deal.size?, deal.level?)))
+            .collect();
+
+        if fills.is_empty() && self.deal_status == DealStatus::ACCEPTED {
+            return vec![(self.size, self.level)];
+        }
+
+        fills
+    }
+
+    /// Sum of the sizes of the filled affected deals, more than one for orders filled in parts.
+    pub fn total_filled_size(&self) -> f64 {
+        self.fills().iter().map(|(size, _)| size).sum()
+    }
+
+    /// Size weighted average level of the filled affected deals. None if nothing was filled.
+    pub fn average_fill_level(&self) -> Option<f64> {
+        let fills = self.fills();
+        let total_size: f64 = fills.iter().map(|(size, _)| size).sum();
+        if total_size <= 0. {
+            return None;
+        }
+
+        Some(fills.iter().map(|(size, level)| size * level).sum::<f64>() / total_size)
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AffectedDeal {
     pub deal_id: String,
     pub status: Status,
+    /// Filled size of this deal, not sent for every status
+    pub size: Option<f64>,
+    /// Fill level of this deal, not sent for every status
+    pub level: Option<f64>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AllPositionsResponse {
     pub positions: Vec<PositionResponse>,
 }
 
-#[derive(Debug, Deserialize)]
+/// Overview of the current account.
+/// open_pl and margin_used are summed over all positions without currency conversion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountSummary {
+    pub balance: f64,
+    pub available: f64,
+    pub open_pl: f64,
+    pub position_count: usize,
+    /// Estimated from the leverage of the positions, see PositionResponse::margin
+    pub margin_used: f64,
+}
+
+/// State of the account on the broker side, see CapitalDotComInterface::reconcile
+#[derive(Debug)]
+pub struct ReconciliationSnapshot {
+    pub positions: Vec<PositionResponse>,
+    pub working_orders: Vec<WorkingOrder>,
+    /// Activity of the last day
+    pub recent_activity: Vec<Activity>,
+}
+
+/// Margin of a market at the current price, see SingleMarketDetailsResponse::margin_info
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarginInfo {
+    /// Margin for one unit of the instrument, in the currency of the instrument
+    pub margin_per_unit: f64,
+    /// Margin for a size of 1 (lot_size units)
+    pub margin_per_size: f64,
+    pub currency: enums::Currency,
+    /// Largest size whose margin fits into the available balance, rounded down to min_size_increment
+    /// and capped at max_deal_size. Below min_deal_size if not even the smallest order is affordable.
+    pub max_size: f64,
+}
+
+/// Risk figures of a position, see PositionResponse::risk
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionRisk {
+    pub position: PositionResponse,
+    /// In the currency of the position, see PositionResponse::notional_value
+    pub notional_value: f64,
+    /// Estimated from the leverage of the position, see PositionResponse::margin
+    pub margin: f64,
+    /// In points at the prices of the embedded market data, None if the position has no stop
+    pub distance_to_stop: Option<f64>,
+}
+
+/// Difference between two snapshots of the open positions, matched by deal_id.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PositionDiff {
+    /// Positions that are only in the new snapshot
+    pub opened: Vec<PositionResponse>,
+    /// Positions that are only in the old snapshot
+    pub closed: Vec<PositionResponse>,
+    /// Positions whose size changed as (old, new)
+    pub changed: Vec<(PositionResponse, PositionResponse)>,
+}
+
+/// Find the positions that were opened, closed or changed in size between the two snapshots.
+pub fn diff_positions(old: &AllPositionsResponse, new: &AllPositionsResponse) -> PositionDiff {
+    let old_positions: HashMap<&str, &PositionResponse> = old
+        .positions
+        .iter()
+        .map(|position| (position.position.deal_id.as_str(), position))
+        .collect();
+    let new_positions: HashMap<&str, &PositionResponse> = new
+        .positions
+        .iter()
+        .map(|position| (position.position.deal_id.as_str(), position))
+        .collect();
+
+    let mut position_diff = PositionDiff::default();
+    for position in &new.positions {
+        match old_positions.get(position.position.deal_id.as_str()) {
+            None => position_diff.opened.push(position.clone()),
+            Some(old_position) if old_position.position.size != position.position.size => {
+                position_diff
+                    .changed
+                    .push(((*old_position).clone(), position.clone()));
+            }
+            Some(_) => {}
+        }
+    }
+
+    for position in &old.positions {
+        if !new_positions.contains_key(position.position.deal_id.as_str()) {
+            position_diff.closed.push(position.clone());
+        }
+    }
+
+    position_diff
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PositionResponse {
     pub position: PositionData,
     pub market: MarketPosition,
 }
+impl PositionResponse {
+    /// Value of a move of one point for a size of 1 in the currency of the position.
+    /// contract_size / scaling_factor
+    pub fn value_per_point(&self) -> f64 {
+        value_per_point(
+            self.position.contract_size as f64,
+            self.market.scaling_factor,
+        )
+    }
 
-#[derive(Debug, Deserialize)]
+    /// Notional value of the position in the currency of the position.
+    /// size * level * value_per_point
+    pub fn notional_value(&self) -> f64 {
+        self.position.size * self.position.level * self.value_per_point()
+    }
+
+    /// Estimated margin of the position in the currency of the position.
+    /// notional_value / leverage
+    pub fn margin(&self) -> f64 {
+        self.notional_value() / self.position.leverage.max(1) as f64
+    }
+
+    /// Notional value converted into the given currency.
+    /// rates maps a currency to the rate for converting one unit of it into `currency`.
+    /// Returns None if no rate for the currency of the position is given.
+    pub fn exposure_in(
+        &self,
+        currency: enums::Currency,
+        rates: &HashMap<enums::Currency, f64>,
+    ) -> Option<f64> {
+        if self.position.currency == currency {
+            return Some(self.notional_value());
+        }
+
+        rates
+            .get(&self.position.currency)
+            .map(|rate| self.notional_value() * rate)
+    }
+
+    /// Client side estimate of the profit/loss in the currency of the position.
+    /// Long positions are valued at the bid, short positions at the offer of the embedded market data.
+    /// The authoritative value from the broker is PositionData::upl.
+    pub fn computed_pl(&self) -> f64 {
+        let price_difference = match self.position.direction {
+            enums::Direction::BUY => self.market.bid - self.position.level,
+            enums::Direction::SELL => self.position.level - self.market.offer,
+        };
+
+        price_difference * self.position.size * self.value_per_point()
+    }
+
+    /// Distance between the closing price (bid for BUY, offer for SELL) and the stop level in points (price units).
+    /// Negative if the price already crossed the stop. None if the position has no stop.
+    pub fn distance_to_stop(&self, bid: f64, offer: f64) -> Option<f64> {
+        let stop_level = self.position.stop_level?;
+
+        Some(match self.position.direction {
+            enums::Direction::BUY => bid - stop_level,
+            enums::Direction::SELL => stop_level - offer,
+        })
+    }
+
+    /// distance_to_stop in pips, pip_position is the decimal place of a pip (see Market::pip_position).
+    pub fn distance_to_stop_in_pips(&self, bid: f64, offer: f64, pip_position: i32) -> Option<f64> {
+        Some(self.distance_to_stop(bid, offer)? * 10f64.powi(pip_position))
+    }
+
+    /// Notional value, margin and distance to stop at the prices of the embedded market data.
+    pub fn risk(&self) -> PositionRisk {
+        PositionRisk {
+            position: self.clone(),
+            notional_value: self.notional_value(),
+            margin: self.margin(),
+            distance_to_stop: self.distance_to_stop(self.market.bid, self.market.offer),
+        }
+    }
+
+    /// Body for a position with the same epic and size in the opposite direction.
+    pub fn reverse_body(&self) -> request_bodies::CreatePositionBody {
+        request_bodies::CreatePositionBody::new(
+            self.position.direction.opposite(),
+            &self.market.epic,
+            self.position.size,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PositionData {
-    pub contract_size: i32,
+    /// 1 if missing
+    #[serde(
+        default = "default_contract_size",
+        deserialize_with = "deserialize_whole_number"
+    )]
+    pub contract_size: i64,
     pub created_date: String,
     pub created_date_UTC: String,
     pub deal_id: String,
     pub deal_reference: String,
-    pub working_order_id: String,
-    pub size: f32,
-    pub leverage: i8,
-    pub upl: f32,
+    /// Only set for positions opened by a working order
+    pub working_order_id: Option<String>,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub size: f64,
+    #[serde(deserialize_with = "deserialize_whole_number")]
+    pub leverage: i32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub upl: f64,
     pub direction: enums::Direction,
-    pub level: f32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub level: f64,
     pub currency: enums::Currency,
+    #[serde(default)]
     pub guaranteed_stop: bool,
+    pub stop_level: Option<f64>,
+    pub profit_level: Option<f64>,
+}
+impl PositionData {
+    /// created_date_UTC parsed as a DateTime
+    pub fn created_at(&self) -> Option<DateTime<Utc>> {
+        NaiveDateTime::parse_from_str(&self.created_date_UTC, "%Y-%m-%dT%H:%M:%S%.f")
+            .ok()
+            .map(|created_at| created_at.and_utc())
+    }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MarketPosition {
     pub instrument_name: String,
-    pub expiry: String,
+    pub expiry: enums::Expiry,
     pub market_status: MarketStatus,
     pub epic: String,
     pub symbol: String,
     pub instrument_type: enums::InstrumentType,
     pub lot_size: i32,
-    pub high: f32,
-    pub low: f32,
-    pub percentage_change: f32,
-    pub net_change: f32,
-    pub bid: f32,
-    pub offer: f32,
+    pub high: f64,
+    pub low: f64,
+    pub percentage_change: f64,
+    pub net_change: f64,
+    pub bid: f64,
+    pub offer: f64,
     pub update_time: String,
     pub update_time_UTC: String,
-    pub delay_time: f32,
+    pub delay_time: f64,
     pub streaming_prices_available: bool,
-    pub scaling_factor: f32,
+    pub scaling_factor: f64,
     pub market_modes: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct AllWorkingOrdersResponse {
+    pub working_orders: Vec<WorkingOrder>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkingOrder {
+    pub working_order_data: WorkingOrderData,
+    pub market_data: MarketPosition,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkingOrderData {
+    pub deal_id: String,
+    pub direction: enums::Direction,
+    pub epic: String,
+    pub order_size: f64,
+    #[serde(deserialize_with = "deserialize_whole_number")]
+    pub leverage: i32,
+    pub order_level: f64,
+    pub time_in_force: enums::TimeInForce,
+    pub good_till_date: Option<String>,
+    pub created_date: String,
+    #[serde(rename = "createdDateUTC")]
+    pub created_date_utc: String,
+    pub guaranteed_stop: bool,
+    pub order_type: enums::OrderType,
+    pub stop_level: Option<f64>,
+    pub stop_distance: Option<f64>,
+    pub profit_level: Option<f64>,
+    pub profit_distance: Option<f64>,
+    pub trailing_stop: bool,
+    pub currency_code: enums::Currency,
+}
+impl WorkingOrderData {
+    /// good_till_date (local time of the session) in UTC
+    pub fn good_till(&self, timezone: FixedOffset) -> Option<DateTime<Utc>> {
+        parse_local_time(self.good_till_date.as_deref()?, timezone)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityHistoryResponse {
+    pub activities: Vec<Activity>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Activity {
+    pub date: String,
+    #[serde(rename = "dateUTC")]
+    pub date_utc: String,
+    pub epic: String,
+    pub deal_id: String,
+    pub source: String,
+    #[serde(rename = "type")]
+    pub activity_type: enums::ActivityType,
+    pub status: String,
+    pub details: Option<ActivityDetails>,
+}
+impl Activity {
+    /// date_utc parsed as a DateTime
+    pub fn date_time(&self) -> Option<DateTime<Utc>> {
+        NaiveDateTime::parse_from_str(&self.date_utc, "%Y-%m-%dT%H:%M:%S%.f")
+            .ok()
+            .map(|date| date.and_utc())
+    }
+
+    /// Check if a position was opened by this activity. Needs the details of the activity.
+    pub fn is_position_opened(&self) -> bool {
+        self.activity_type == enums::ActivityType::Position
+            && self.details.as_ref().is_some_and(|details| {
+                details
+                    .actions
+                    .iter()
+                    .any(|action| action.action_type == "POSITION_OPENED")
+            })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityDetails {
+    #[serde(default)]
+    pub actions: Vec<ActivityAction>,
+    pub size: Option<f64>,
+    pub level: Option<f64>,
+    pub direction: Option<enums::Direction>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityAction {
+    pub action_type: String,
+    pub affected_deal_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionHistoryResponse {
+    pub transactions: Vec<Transaction>,
+}
+impl TransactionHistoryResponse {
+    /// Sum of the realized profit/loss of the TRADE transactions per currency.
+    pub fn realized_pl_by_currency(&self) -> HashMap<enums::Currency, f64> {
+        let mut realized_pl = HashMap::new();
+        for transaction in &self.transactions {
+            if let Some(profit_and_loss) = transaction.profit_and_loss() {
+                *realized_pl.entry(transaction.currency).or_insert(0.) += profit_and_loss;
+            }
+        }
+
+        realized_pl
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transaction {
+    pub date: String,
+    pub date_utc: String,
+    pub instrument_name: String,
+    pub transaction_type: enums::TransactionType,
+    pub note: String,
+    pub reference: String,
+    /// Amount booked to the account, the realized profit/loss for TRADE transactions.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub size: f64,
+    pub currency: enums::Currency,
+    pub status: String,
+}
+impl Transaction {
+    /// Realized profit/loss if this is a TRADE transaction.
+    pub fn profit_and_loss(&self) -> Option<f64> {
+        (self.transaction_type == enums::TransactionType::Trade).then_some(self.size)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Market {
-    pub delay_time: f32,
+    pub delay_time: f64,
     pub epic: String,
     pub symbol: String,
-    //pub net_change: f32,
+    //pub net_change: f64,
     pub lot_size: i32,
-    pub expiry: String,
+    pub expiry: enums::Expiry,
     pub instrument_type: enums::InstrumentType,
     pub instrument_name: String,
-    //pub high: f32,
-    //pub low: f32,
-    pub percentage_change: f32,
+    //pub high: f64,
+    //pub low: f64,
+    pub percentage_change: f64,
     pub update_time: String,
     pub update_time_UTC: String,
-    pub bid: f32,
-    pub offer: f32,
+    pub bid: f64,
+    pub offer: f64,
     pub streaming_prices_available: bool,
     pub market_status: MarketStatus,
-    pub scaling_factor: i32,
+    pub scaling_factor: f64,
+    #[serde(default)]
     pub market_modes: Vec<String>,
-    pub pip_position: i32,
-    pub tick_size: f32,
+    /// Missing for some instruments, e.g. shares
+    #[serde(default)]
+    pub pip_position: Option<i32>,
+    #[serde(default)]
+    pub tick_size: Option<f64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -229,6 +782,22 @@ pub struct MarketDetailsResponse {
     pub markets: Vec<Market>,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarketNavigationResponse {
+    pub nodes: Vec<MarketNavigationNode>,
+    /// Only set for sub nodes
+    #[serde(default)]
+    pub markets: Vec<Market>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarketNavigationNode {
+    pub id: String,
+    pub name: String,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SingleMarketDetailsResponse {
@@ -236,13 +805,75 @@ pub struct SingleMarketDetailsResponse {
     pub dealing_rules: DealingRules,
     pub snapshot: Snapshot,
 }
+impl SingleMarketDetailsResponse {
+    /// Whether positions in this market can have a guaranteed stop.
+    pub fn guaranteed_stops_allowed(&self) -> bool {
+        self.instrument.guaranteed_stop_allowed
+    }
+
+    /// Size for the API from units of the instrument (e.g. shares, barrels, currency units).
+    /// One size is lot_size units, the size is rounded down to the min_size_increment.
+    /// Check the result with DealingRules::is_valid_deal_size.
+    pub fn size_from_units(&self, units: f64) -> f64 {
+        self.dealing_rules
+            .round_size(units / self.instrument.lot_size.max(1) as f64)
+    }
+
+    /// Units of the instrument a size of the API stands for.
+    pub fn units_from_size(&self, size: f64) -> f64 {
+        size * self.instrument.lot_size.max(1) as f64
+    }
+
+    /// Value of a move of one point for a size of 1 in the currency of the instrument.
+    /// lot_size (the contract size of a position in this market) / scaling_factor,
+    /// the same value PositionResponse::value_per_point uses for profit/loss.
+    pub fn value_per_point(&self) -> f64 {
+        value_per_point(
+            self.instrument.lot_size.max(1) as f64,
+            self.snapshot.scaling_factor,
+        )
+    }
+
+    /// Margin for one unit at the current offer in the currency of the instrument.
+    /// offer * margin_factor / 100 for a margin factor in PERCENTAGE, margin_factor for POINTS.
+    pub fn margin_per_unit(&self) -> f64 {
+        match self.instrument.margin_factor_unit {
+            enums::Unit::PERCENTAGE => {
+                self.snapshot.offer * self.instrument.margin_factor as f64 / 100.
+            }
+            enums::Unit::POINTS => self.instrument.margin_factor as f64,
+        }
+    }
+
+    /// Margin at the current price and the largest size affordable with available
+    /// (in the currency of the instrument).
+    pub fn margin_info(&self, available: f64) -> MarginInfo {
+        let margin_per_unit = self.margin_per_unit();
+        let margin_per_size = self.units_from_size(1.) * margin_per_unit;
+        let max_deal_size = self.dealing_rules.max_deal_size.value;
+        let max_size = if margin_per_size > 0. {
+            self.dealing_rules
+                .round_size(available.max(0.) / margin_per_size)
+                .min(max_deal_size)
+        } else {
+            max_deal_size
+        };
+
+        MarginInfo {
+            margin_per_unit,
+            margin_per_size,
+            currency: self.instrument.currency,
+            max_size,
+        }
+    }
+}
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Instrument {
     pub epic: String,
     pub symbol: String,
-    pub expiry: String,
+    pub expiry: enums::Expiry,
     pub name: String,
     pub lot_size: i32,
     // pub type: enums::InstrumentType,
@@ -251,8 +882,11 @@ pub struct Instrument {
     pub currency: enums::Currency,
     pub margin_factor: i32,
     pub margin_factor_unit: enums::Unit,
-    pub opening_hours: OpeningHours,
-    pub overnight_fee: OvernightFee,
+    #[serde(default)]
+    pub opening_hours: Option<OpeningHours>,
+    /// Missing for instruments without overnight financing
+    #[serde(default)]
+    pub overnight_fee: Option<OvernightFee>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -262,17 +896,104 @@ pub struct DealingRules {
     pub min_deal_size: UnitValue,
     pub max_deal_size: UnitValue,
     pub min_size_increment: UnitValue,
-    pub min_guaranteed_stop_distance: UnitValue,
+    /// Missing for markets without guaranteed stops
+    #[serde(default)]
+    pub min_guaranteed_stop_distance: Option<UnitValue>,
     pub min_stop_or_profit_distance: UnitValue,
     pub max_stop_or_profit_distance: UnitValue,
     pub market_order_preference: String,
     pub trailing_stops_preference: String,
 }
+impl DealingRules {
+    /// Round the size down to a multiple of min_size_increment.
+    pub fn round_size(&self, size: f64) -> f64 {
+        let increment = self.min_size_increment.value;
+        if increment <= 0. {
+            return size;
+        }
+
+        // Small epsilon against float errors like 2.9999998 / 0.1
+        (size / increment + 1e-4).floor() * increment
+    }
+
+    /// Check the size against min_deal_size and max_deal_size.
+    pub fn is_valid_deal_size(&self, size: f64) -> bool {
+        size >= self.min_deal_size.value && size <= self.max_deal_size.value
+    }
+
+    /// min_guaranteed_stop_distance in points, a limit given as a percentage relative to current_price.
+    /// None if the market has no limit.
+    pub fn min_guaranteed_stop_distance_at(&self, current_price: f64) -> Option<f64> {
+        let limit = self.min_guaranteed_stop_distance.as_ref()?;
+
+        Some(match limit.unit {
+            enums::Unit::POINTS => limit.value,
+            enums::Unit::PERCENTAGE => current_price * limit.value / 100.,
+        })
+    }
+
+    /// Check a guaranteed stop distance against min_guaranteed_stop_distance, see min_guaranteed_stop_distance_at.
+    /// A market without a limit accepts every positive distance.
+    pub fn is_valid_guaranteed_stop_distance_at(&self, distance: f64, current_price: f64) -> bool {
+        distance > 0.
+            && !matches!(
+                self.min_guaranteed_stop_distance_at(current_price),
+                Some(min_distance) if distance < min_distance
+            )
+    }
+
+    /// Check a stop or profit distance against min_stop_or_profit_distance and max_stop_or_profit_distance.
+    /// Limits given as a percentage are checked relative to current_price.
+    pub fn is_valid_stop_or_profit_distance_at(&self, distance: f64, current_price: f64) -> bool {
+        let in_points = |limit: &UnitValue| match limit.unit {
+            enums::Unit::POINTS => limit.value,
+            enums::Unit::PERCENTAGE => current_price * limit.value / 100.,
+        };
+
+        distance > 0.
+            && distance >= in_points(&self.min_stop_or_profit_distance)
+            && distance <= in_points(&self.max_stop_or_profit_distance)
+    }
+}
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Snapshot {
     pub market_status: MarketStatus,
+    pub bid: f64,
+    pub offer: f64,
+    pub high: f64,
+    pub low: f64,
+    pub percentage_change: f64,
+    pub net_change: f64,
+    /// Local time of the session, see parse_local_time
+    pub update_time: String,
+    pub delay_time: f64,
+    pub decimal_places_factor: i32,
+    pub scaling_factor: f64,
+    #[serde(default)]
+    pub market_modes: Vec<String>,
+}
+impl Snapshot {
+    /// Market mode in which no positions can be opened, even if the market is TRADEABLE
+    const NO_NEW_POSITION_MODE: &'static str = "NO_NEW_POSITION";
+
+    /// Price a new position in the direction is opened at: the offer for BUY, the bid for SELL.
+    pub fn opening_price(&self, direction: enums::Direction) -> f64 {
+        match direction {
+            enums::Direction::BUY => self.offer,
+            enums::Direction::SELL => self.bid,
+        }
+    }
+
+    /// Whether new positions can be opened: the status is TRADEABLE and no market mode forbids new positions.
+    pub fn is_tradeable(&self) -> bool {
+        self.market_status.is_tradeable()
+            && !self
+                .market_modes
+                .iter()
+                .any(|mode| mode == Self::NO_NEW_POSITION_MODE)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -287,12 +1008,83 @@ pub struct OpeningHours {
     pub sun: Vec<String>,
     pub zone: enums::TimeZone,
 }
+impl OpeningHours {
+    /// Opening hours of the day as UTC ranges (start, end).
+    /// Ranges like "00:00 - 00:00" or "22:00 - 00:00" end at midnight of the following day.
+    /// None if the zone is a named zone without a fixed offset or a range can not be parsed.
+    pub fn ranges_on(&self, date: NaiveDate) -> Option<Vec<(DateTime<Utc>, DateTime<Utc>)>> {
+        let offset = self.zone.offset()?;
+        let ranges = match date.weekday() {
+            Weekday::Mon => &self.mon,
+            Weekday::Tue => &self.tue,
+            Weekday::Wed => &self.wed,
+            Weekday::Thu => &self.thu,
+            Weekday::Fri => &self.fri,
+            Weekday::Sat => &self.sat,
+            Weekday::Sun => &self.sun,
+        };
+
+        ranges
+            .iter()
+            .map(|range| {
+                let (start, end) = range.split_once('-')?;
+                let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").ok()?;
+                let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").ok()?;
+
+                let start_date = date.and_time(start);
+                let end_date = if end <= start {
+                    date.succ_opt()?.and_time(end)
+                } else {
+                    date.and_time(end)
+                };
+
+                Some((
+                    start_date.and_local_timezone(offset).single()?.to_utc(),
+                    end_date.and_local_timezone(offset).single()?.to_utc(),
+                ))
+            })
+            .collect()
+    }
+
+    /// Open ranges (see ranges_on) between from and to in UTC, cut to from and to.
+    /// Ranges that end when the next one starts (e.g. "22:00 - 00:00" and "00:00 - 21:00") are merged.
+    /// None if the ranges of a day are unavailable (see ranges_on).
+    pub fn open_ranges(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Option<Vec<(DateTime<Utc>, DateTime<Utc>)>> {
+        let mut ranges = Vec::new();
+        // The day in the zone of the opening hours can start on the previous UTC day
+        let mut date = from.date_naive().pred_opt()?;
+        while date <= to.date_naive() {
+            for (start, end) in self.ranges_on(date)? {
+                let (start, end) = (start.max(from), end.min(to));
+                if start < end {
+                    ranges.push((start, end));
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        ranges.sort();
+
+        let mut open_ranges: Vec<(DateTime<Utc>, DateTime<Utc>)> = Vec::new();
+        for (start, end) in ranges {
+            match open_ranges.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => open_ranges.push((start, end)),
+            }
+        }
+
+        Some(open_ranges)
+    }
+}
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct OvernightFee {
-    pub long_rate: f32,
-    pub short_rate: f32,
+    pub long_rate: f64,
+    pub short_rate: f64,
     pub swap_charge_timestamp: Timestamp,
     pub swap_charge_interval: i32,
 }
@@ -301,7 +1093,7 @@ pub struct OvernightFee {
 #[serde(rename_all = "camelCase")]
 pub struct UnitValue {
     pub unit: enums::Unit,
-    pub value: f32,
+    pub value: f64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -320,31 +1112,904 @@ pub struct Prices {
     pub close_price: Price,
     pub high_price: Price,
     pub low_price: Price,
-    pub last_traded_volume: f32,
+    pub last_traded_volume: f64,
+}
+impl Prices {
+    /// snapshot_time_UTC parsed as a DateTime, the time to sort and plot by
+    pub fn snapshot_time_utc(&self) -> Option<DateTime<Utc>> {
+        NaiveDateTime::parse_from_str(&self.snapshot_time_UTC, "%Y-%m-%dT%H:%M:%S%.f")
+            .ok()
+            .map(|time| time.and_utc())
+    }
+
+    /// snapshot_time (local time of the broker) with the offset of the session,
+    /// e.g. CapitalDotComAPI::session_timezone
+    pub fn snapshot_time_local(&self, timezone: FixedOffset) -> Option<DateTime<FixedOffset>> {
+        NaiveDateTime::parse_from_str(&self.snapshot_time, "%Y-%m-%dT%H:%M:%S%.f")
+            .ok()?
+            .and_local_timezone(timezone)
+            .single()
+    }
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Price {
-    pub bid: f32,
-    pub ask: f32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub bid: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub ask: f64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[allow(non_camel_case_types)]
 pub enum MarketStatus {
     TRADEABLE,
     CLOSED,
+    /// Only stops and limits of existing positions and orders can be edited
+    EDITS_ONLY,
+    OFFLINE,
+    ON_AUCTION,
+    ON_AUCTION_NO_EDITS,
+    SUSPENDED,
+    #[serde(other)]
+    Unknown,
+}
+impl MarketStatus {
+    /// Only TRADEABLE allows opening positions.
+    pub fn is_tradeable(&self) -> bool {
+        matches!(self, Self::TRADEABLE)
+    }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
 pub enum Status {
     OPEN,
     OPENED,
     PENDING,
+    CLOSED,
+    DELETED,
+    REJECTED,
+    #[default]
+    #[serde(other)]
+    Unknown,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, PartialEq, Deserialize)]
+pub enum LogoutStatus {
+    SUCCESS,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
 pub enum DealStatus {
     ACCEPTED,
+    REJECTED,
     DECLINED,
+    #[serde(other)]
+    Unknown,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position_json(direction: &str, size: f64, level: f64) -> String {
+        format!(
+            r#"{{
+                "position": {{
+                    "contractSize": 1,
+                    "createdDate": "2024-06-11T15:00:00.000",
+                    "createdDateUTC": "2024-06-11T13:00:00.000",
+                    "dealId": "0001",
+                    "dealReference": "p_0001",
+                    "workingOrderId": "0002",
+                    "size": {size},
+                    "leverage": 5,
+                    "upl": 0.0,
+                    "direction": "{direction}",
+                    "level": {level},
+                    "currency": "USD",
+                    "guaranteedStop": false
+                }},
+                "market": {{
+                    "instrumentName": "Tesla Inc",
+                    "expiry": "-",
+                    "marketStatus": "TRADEABLE",
+                    "epic": "TSLA",
+                    "symbol": "TSLA",
+                    "instrumentType": "SHARES",
+                    "lotSize": 1,
+                    "high": 180.0,
+                    "low": 170.0,
+                    "percentageChange": 1.0,
+                    "netChange": 1.5,
+                    "bid": 175.0,
+                    "offer": 175.5,
+                    "updateTime": "2024-06-11T15:00:00.000",
+                    "updateTimeUTC": "2024-06-11T13:00:00.000",
+                    "delayTime": 0,
+                    "streamingPricesAvailable": true,
+                    "scalingFactor": 1,
+                    "marketModes": ["REGULAR"]
+                }}
+            }}"#
+        )
+    }
+
+    const WORKING_ORDERS_JSON: &str = r#"{
+        "workingOrders": [
+            {
+                "workingOrderData": {
+                    "dealId": "0003",
+                    "direction": "BUY",
+                    "epic": "SILVER",
+                    "orderSize": 1,
+                    "leverage": 20,
+                    "orderLevel": 20.5,
+                    "timeInForce": "GOOD_TILL_CANCELLED",
+                    "createdDate": "2024-06-11T15:00:00.000",
+                    "createdDateUTC": "2024-06-11T13:00:00.000",
+                    "guaranteedStop": false,
+                    "orderType": "LIMIT",
+                    "stopDistance": 5,
+                    "trailingStop": false,
+                    "currencyCode": "USD"
+                },
+                "marketData": {
+                    "instrumentName": "Silver",
+                    "expiry": "-",
+                    "marketStatus": "TRADEABLE",
+                    "epic": "SILVER",
+                    "symbol": "Silver",
+                    "instrumentType": "COMMODITIES",
+                    "lotSize": 1,
+                    "high": 30.1,
+                    "low": 29.4,
+                    "percentageChange": 0.5,
+                    "netChange": 0.15,
+                    "bid": 29.8,
+                    "offer": 29.83,
+                    "updateTime": "2024-06-11T15:00:00.000",
+                    "updateTimeUTC": "2024-06-11T13:00:00.000",
+                    "delayTime": 0,
+                    "streamingPricesAvailable": true,
+                    "scalingFactor": 1,
+                    "marketModes": ["REGULAR"]
+                }
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn position_without_working_order() {
+        let position: PositionData = serde_json::from_str(
+            r#"{
+                "createdDate": "2024-06-11T15:00:00.000",
+                "createdDateUTC": "2024-06-11T13:00:00.000",
+                "dealId": "0001",
+                "dealReference": "p_0001",
+                "size": 1,
+                "leverage": 5,
+                "upl": -0.5,
+                "direction": "BUY",
+                "level": 170.0,
+                "currency": "USD"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(position.working_order_id, None);
+        assert_eq!(position.contract_size, 1);
+        assert!(!position.guaranteed_stop);
+    }
+
+    #[test]
+    fn high_leverage_position() {
+        let position = |leverage: &str, contract_size: &str| {
+            serde_json::from_str::<PositionData>(&format!(
+                r#"{{
+                    "contractSize": {contract_size},
+                    "createdDate": "2024-06-11T15:00:00.000",
+                    "createdDateUTC": "2024-06-11T13:00:00.000",
+                    "dealId": "0001",
+                    "dealReference": "p_0001",
+                    "size": 0.01,
+                    "leverage": {leverage},
+                    "upl": 1.5,
+                    "direction": "SELL",
+                    "level": 1.0850,
+                    "currency": "USD"
+                }}"#
+            ))
+        };
+
+        let position_data = position("200", "100000").unwrap();
+        assert_eq!(position_data.leverage, 200);
+        assert_eq!(position_data.contract_size, 100000);
+
+        let position_data = position("500.0", "5000000000.0").unwrap();
+        assert_eq!(position_data.leverage, 500);
+        assert_eq!(position_data.contract_size, 5000000000);
+
+        assert!(position("0.5", "1").is_err());
+    }
+
+    #[test]
+    fn single_market_details() {
+        let mut market: SingleMarketDetailsResponse = serde_json::from_str(
+            r#"{
+                "instrument": {
+                    "epic": "TSLA",
+                    "symbol": "TSLA",
+                    "expiry": "-",
+                    "name": "Tesla Inc",
+                    "lotSize": 1,
+                    "type": "SHARES",
+                    "guaranteedStopAllowed": true,
+                    "streamingPricesAvailable": true,
+                    "currency": "USD",
+                    "marginFactor": 20,
+                    "marginFactorUnit": "PERCENTAGE",
+                    "openingHours": {
+                        "mon": ["13:30 - 20:00"],
+                        "tue": ["13:30 - 20:00"],
+                        "wed": ["13:30 - 20:00"],
+                        "thu": ["13:30 - 20:00"],
+                        "fri": ["13:30 - 20:00"],
+                        "sat": [],
+                        "sun": [],
+                        "zone": "UTC"
+                    },
+                    "overnightFee": {
+                        "longRate": -0.0252,
+                        "shortRate": 0.0029,
+                        "swapChargeTimestamp": 1718139600000,
+                        "swapChargeInterval": 1440
+                    }
+                },
+                "dealingRules": {
+                    "minStepDistance": {"unit": "POINTS", "value": 0.01},
+                    "minDealSize": {"unit": "POINTS", "value": 1.0},
+                    "maxDealSize": {"unit": "POINTS", "value": 1000.0},
+                    "minSizeIncrement": {"unit": "POINTS", "value": 1.0},
+                    "minGuaranteedStopDistance": {"unit": "PERCENTAGE", "value": 5.0},
+                    "minStopOrProfitDistance": {"unit": "PERCENTAGE", "value": 0.1},
+                    "maxStopOrProfitDistance": {"unit": "PERCENTAGE", "value": 75.0},
+                    "marketOrderPreference": "AVAILABLE_DEFAULT_OFF",
+                    "trailingStopsPreference": "AVAILABLE"
+                },
+                "snapshot": {
+                    "marketStatus": "TRADEABLE",
+                    "netChange": 1.52,
+                    "percentageChange": 0.87,
+                    "updateTime": "2024-06-11T15:00:00.000",
+                    "delayTime": 0,
+                    "bid": 175.0,
+                    "offer": 175.5,
+                    "high": 178.2,
+                    "low": 172.9,
+                    "decimalPlacesFactor": 2,
+                    "scalingFactor": 1,
+                    "marketModes": ["REGULAR"]
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert!(market.guaranteed_stops_allowed());
+        assert_eq!(market.snapshot.market_status, MarketStatus::TRADEABLE);
+        assert_eq!(market.snapshot.bid, 175.);
+        assert_eq!(market.snapshot.offer, 175.5);
+        assert_eq!(market.snapshot.high, 178.2);
+        assert_eq!(market.snapshot.low, 172.9);
+        assert_eq!(market.snapshot.net_change, 1.52);
+        assert_eq!(market.snapshot.decimal_places_factor, 2);
+        assert!(market.snapshot.is_tradeable());
+
+        market.snapshot.market_modes = vec![String::from("NO_NEW_POSITION")];
+        assert!(!market.snapshot.is_tradeable());
+        market.snapshot.market_modes = vec![String::from("REGULAR")];
+        market.snapshot.market_status = MarketStatus::CLOSED;
+        assert!(!market.snapshot.is_tradeable());
+
+        assert_eq!(market.size_from_units(3.), 3.);
+        assert_eq!(market.units_from_size(3.), 3.);
+
+        // 20% of 175.5
+        assert!((market.margin_per_unit() - 35.1).abs() < 1e-9);
+        let margin_info = market.margin_info(1000.);
+        assert_eq!(margin_info.currency, enums::Currency::USD);
+        assert_eq!(margin_info.max_size, 28.);
+        assert_eq!(market.margin_info(1e9).max_size, 1000.);
+        assert_eq!(market.margin_info(-5.).max_size, 0.);
+
+        market.instrument.lot_size = 100;
+        assert_eq!(market.size_from_units(250.), 2.);
+        assert_eq!(market.units_from_size(2.), 200.);
+        market.dealing_rules.min_size_increment.value = 0.01;
+        assert_eq!(market.size_from_units(250.), 2.5);
+    }
+
+    #[test]
+    fn market_status() {
+        let parse =
+            |status: &str| serde_json::from_str::<MarketStatus>(&format!(r#""{status}""#)).unwrap();
+
+        assert_eq!(parse("TRADEABLE"), MarketStatus::TRADEABLE);
+        assert_eq!(parse("CLOSED"), MarketStatus::CLOSED);
+        assert_eq!(parse("EDITS_ONLY"), MarketStatus::EDITS_ONLY);
+        assert_eq!(parse("OFFLINE"), MarketStatus::OFFLINE);
+        assert_eq!(parse("ON_AUCTION"), MarketStatus::ON_AUCTION);
+        assert_eq!(
+            parse("ON_AUCTION_NO_EDITS"),
+            MarketStatus::ON_AUCTION_NO_EDITS
+        );
+        assert_eq!(parse("SUSPENDED"), MarketStatus::SUSPENDED);
+        assert_eq!(parse("ON_QUOTE"), MarketStatus::Unknown);
+
+        assert!(MarketStatus::TRADEABLE.is_tradeable());
+        assert!(!MarketStatus::EDITS_ONLY.is_tradeable());
+        assert!(!MarketStatus::Unknown.is_tradeable());
+    }
+
+    #[test]
+    fn account_currencies() {
+        let account = |account_id: &str, currency: &str| {
+            format!(
+                r#"{{
+                    "accountId": "{account_id}",
+                    "accountName": "{currency}",
+                    "status": "ENABLED",
+                    "accountType": "CFD",
+                    "preferred": false,
+                    "balance": {{"balance": 0.0, "deposit": 0.0, "profitLoss": 0.0, "available": 0.0}},
+                    "currency": "{currency}",
+                    "symbol": "$"
+                }}"#
+            )
+        };
+        let all_accounts: AllAccountsResponse = serde_json::from_str(&format!(
+            r#"{{"accounts": [{}, {}, {}]}}"#,
+            account("0001", "USD"),
+            account("0002", "EUR"),
+            account("0003", "USD")
+        ))
+        .unwrap();
+
+        assert_eq!(
+            all_accounts.currencies(),
+            vec![enums::Currency::EUR, enums::Currency::USD]
+        );
+    }
+
+    #[test]
+    fn working_orders() {
+        let response: AllWorkingOrdersResponse = serde_json::from_str(WORKING_ORDERS_JSON).unwrap();
+        let order = &response.working_orders[0].working_order_data;
+
+        assert_eq!(order.deal_id, "0003");
+        assert_eq!(order.order_level, 20.5);
+        assert_eq!(order.stop_distance, Some(5.));
+        assert_eq!(order.profit_distance, None);
+        assert!(matches!(order.order_type, enums::OrderType::LIMIT));
+    }
+
+    fn position(direction: &str, size: f64, level: f64) -> PositionResponse {
+        serde_json::from_str(&position_json(direction, size, level)).unwrap()
+    }
+
+    #[test]
+    fn numbers_from_strings() {
+        let json = position_json("BUY", 10., 170.)
+            .replace(r#""size": 10,"#, r#""size": "10.0","#)
+            .replace(r#""upl": 0.0,"#, r#""upl": "-1.5","#)
+            .replace(r#""level": 170,"#, r#""level": "170.25","#);
+        let position: PositionResponse = serde_json::from_str(&json).unwrap();
+        assert_eq!(position.position.size, 10.);
+        assert_eq!(position.position.upl, -1.5);
+        assert_eq!(position.position.level, 170.25);
+
+        let price: Price = serde_json::from_str(r#"{"bid": "1.5", "ask": 1.75}"#).unwrap();
+        assert_eq!(price.bid, 1.5);
+        assert_eq!(price.ask, 1.75);
+
+        assert!(serde_json::from_str::<Price>(r#"{"bid": "abc", "ask": 1.75}"#).is_err());
+    }
+
+    #[test]
+    fn high_digit_prices() {
+        // Both would be rounded by an f32 (to 65432.125 and 1.0876543)
+        let price: Price =
+            serde_json::from_str(r#"{"bid": 65432.12345, "ask": "1.08765432"}"#).unwrap();
+        assert_eq!(price.bid, 65432.12345);
+        assert_eq!(price.ask, 1.08765432);
+        assert_ne!(price.bid as f32 as f64, price.bid);
+    }
+
+    fn unit_value(unit: enums::Unit, value: f64) -> UnitValue {
+        UnitValue { unit, value }
+    }
+
+    fn dealing_rules(stop_or_profit_unit: enums::Unit) -> DealingRules {
+        DealingRules {
+            min_step_distance: unit_value(enums::Unit::POINTS, 0.01),
+            min_deal_size: unit_value(enums::Unit::POINTS, 1.),
+            max_deal_size: unit_value(enums::Unit::POINTS, 1000.),
+            min_size_increment: unit_value(enums::Unit::POINTS, 1.),
+            min_guaranteed_stop_distance: Some(unit_value(enums::Unit::PERCENTAGE, 5.)),
+            min_stop_or_profit_distance: unit_value(stop_or_profit_unit, 1.),
+            max_stop_or_profit_distance: unit_value(stop_or_profit_unit, 100.),
+            market_order_preference: String::from("AVAILABLE_DEFAULT_ON"),
+            trailing_stops_preference: String::from("AVAILABLE"),
+        }
+    }
+
+    #[test]
+    fn stop_or_profit_distance_at() {
+        let rules = dealing_rules(enums::Unit::PERCENTAGE);
+        assert!(rules.is_valid_stop_or_profit_distance_at(2., 200.));
+        assert!(!rules.is_valid_stop_or_profit_distance_at(1.9, 200.));
+        assert!(!rules.is_valid_stop_or_profit_distance_at(0., 0.));
+
+        let rules = dealing_rules(enums::Unit::POINTS);
+        assert!(rules.is_valid_stop_or_profit_distance_at(1., 50.));
+        assert!(rules.is_valid_stop_or_profit_distance_at(100., 50.));
+        assert!(!rules.is_valid_stop_or_profit_distance_at(0.5, 50.));
+        assert!(!rules.is_valid_stop_or_profit_distance_at(100.5, 50.));
+    }
+
+    #[test]
+    fn guaranteed_stop_distance_at() {
+        // 5% of 200
+        let mut rules = dealing_rules(enums::Unit::POINTS);
+        assert_eq!(rules.min_guaranteed_stop_distance_at(200.), Some(10.));
+        assert!(rules.is_valid_guaranteed_stop_distance_at(10., 200.));
+        assert!(!rules.is_valid_guaranteed_stop_distance_at(9.9, 200.));
+        assert!(!rules.is_valid_guaranteed_stop_distance_at(0., 0.));
+
+        // A limit in points does not depend on the price
+        rules.min_guaranteed_stop_distance = Some(unit_value(enums::Unit::POINTS, 5.));
+        assert_eq!(rules.min_guaranteed_stop_distance_at(200.), Some(5.));
+        assert!(rules.is_valid_guaranteed_stop_distance_at(5., 1000.));
+        assert!(!rules.is_valid_guaranteed_stop_distance_at(4.9, 1000.));
+
+        rules.min_guaranteed_stop_distance = None;
+        assert_eq!(rules.min_guaranteed_stop_distance_at(200.), None);
+        assert!(rules.is_valid_guaranteed_stop_distance_at(1., 200.));
+    }
+
+    #[test]
+    fn api_error() {
+        let api_error: APIError =
+            serde_json::from_str(r#"{"errorCode": "error.invalid.session.token"}"#).unwrap();
+        assert_eq!(api_error.error_code, "error.invalid.session.token");
+        assert!(api_error.errors.is_none());
+
+        let api_error: APIError = serde_json::from_str(
+            r#"{
+                "errorCode": "error.invalid.details",
+                "errors": [{"field": "stopDistance", "errorCode": "error.invalid.stopdistance.minvalue"}]
+            }"#,
+        )
+        .unwrap();
+        let errors = api_error.errors.unwrap();
+        assert_eq!(errors[0].field.as_deref(), Some("stopDistance"));
+        assert_eq!(
+            errors[0].error_code.as_deref(),
+            Some("error.invalid.stopdistance.minvalue")
+        );
+        assert!(errors[0].message.is_none());
+    }
+
+    #[test]
+    fn deal_size() {
+        let mut rules = dealing_rules(enums::Unit::POINTS);
+        assert_eq!(rules.round_size(12.7), 12.);
+        assert!(rules.is_valid_deal_size(1.));
+        assert!(rules.is_valid_deal_size(1000.));
+        assert!(!rules.is_valid_deal_size(0.5));
+        assert!(!rules.is_valid_deal_size(1000.5));
+
+        rules.min_size_increment.value = 0.1;
+        assert!((rules.round_size(3.) - 3.).abs() < 1e-5);
+        assert!((rules.round_size(2.96) - 2.9).abs() < 1e-5);
+    }
+
+    #[test]
+    fn position_created_at() {
+        let mut position = position("BUY", 1., 1.);
+        assert_eq!(
+            position.position.created_at(),
+            DateTime::from_timestamp_millis(1718110800000)
+        );
+
+        position.position.created_date_UTC = String::from("2024-06-11T13:00:00");
+        assert_eq!(
+            position.position.created_at(),
+            DateTime::from_timestamp_millis(1718110800000)
+        );
+    }
+
+    #[test]
+    fn reverse_body() {
+        let position = position("BUY", 3., 100.);
+        let body = serde_json::to_value(position.reverse_body()).unwrap();
+
+        assert_eq!(body["direction"], "SELL");
+        assert_eq!(body["epic"], "TSLA");
+        assert_eq!(body["size"], 3.);
+        assert!(!position.reverse_body().matches(&position));
+    }
+
+    #[test]
+    fn computed_pl() {
+        // bid = 175.0, offer = 175.5
+        let long = position("BUY", 2., 170.);
+        assert_eq!(long.computed_pl(), 10.);
+
+        let short = position("SELL", 2., 170.);
+        assert_eq!(short.computed_pl(), -11.);
+
+        let short = position("SELL", 4., 180.);
+        assert_eq!(short.computed_pl(), 18.);
+    }
+
+    #[test]
+    fn activity_history() {
+        let response: ActivityHistoryResponse = serde_json::from_str(
+            r#"{
+                "activities": [
+                    {
+                        "date": "2024-06-11T15:00:00.000",
+                        "dateUTC": "2024-06-11T13:00:00.000",
+                        "epic": "TSLA",
+                        "dealId": "0001",
+                        "source": "USER",
+                        "type": "POSITION",
+                        "status": "ACCEPTED",
+                        "details": {
+                            "actions": [{"actionType": "POSITION_OPENED", "affectedDealId": "0001"}],
+                            "size": 1,
+                            "level": 170.0,
+                            "direction": "BUY"
+                        }
+                    },
+                    {
+                        "date": "2024-06-11T16:00:00.000",
+                        "dateUTC": "2024-06-11T14:00:00.000",
+                        "epic": "TSLA",
+                        "dealId": "0001",
+                        "source": "USER",
+                        "type": "POSITION",
+                        "status": "ACCEPTED",
+                        "details": {
+                            "actions": [{"actionType": "POSITION_CLOSED", "affectedDealId": "0001"}]
+                        }
+                    },
+                    {
+                        "date": "2024-06-11T16:00:00.000",
+                        "dateUTC": "2024-06-11T14:00:00.000",
+                        "epic": "SILVER",
+                        "dealId": "0002",
+                        "source": "SYSTEM",
+                        "type": "SOMETHING_NEW",
+                        "status": "ACCEPTED"
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let activities = response.activities;
+        assert!(activities[0].is_position_opened());
+        assert!(!activities[1].is_position_opened());
+        assert!(!activities[2].is_position_opened());
+        assert_eq!(activities[2].activity_type, enums::ActivityType::Unknown);
+        assert_eq!(
+            activities[0].date_time(),
+            DateTime::from_timestamp_millis(1718110800000)
+        );
+    }
+
+    #[test]
+    fn realized_pl_by_currency() {
+        let response: TransactionHistoryResponse = serde_json::from_str(
+            r#"{
+                "transactions": [
+                    {
+                        "date": "2024-06-11T15:00:00.000",
+                        "dateUtc": "2024-06-11T13:00:00.000",
+                        "instrumentName": "Tesla Inc",
+                        "transactionType": "TRADE",
+                        "note": "Trade closed",
+                        "reference": "0001",
+                        "size": "12.5",
+                        "currency": "USD",
+                        "status": "PROCESSED"
+                    },
+                    {
+                        "date": "2024-06-11T16:00:00.000",
+                        "dateUtc": "2024-06-11T14:00:00.000",
+                        "instrumentName": "Tesla Inc",
+                        "transactionType": "TRADE",
+                        "note": "Trade closed",
+                        "reference": "0002",
+                        "size": "-2.5",
+                        "currency": "USD",
+                        "status": "PROCESSED"
+                    },
+                    {
+                        "date": "2024-06-11T16:00:00.000",
+                        "dateUtc": "2024-06-11T14:00:00.000",
+                        "instrumentName": "Germany 40",
+                        "transactionType": "TRADE",
+                        "note": "Trade closed",
+                        "reference": "0003",
+                        "size": "4",
+                        "currency": "EUR",
+                        "status": "PROCESSED"
+                    },
+                    {
+                        "date": "2024-06-12T00:00:00.000",
+                        "dateUtc": "2024-06-11T22:00:00.000",
+                        "instrumentName": "Tesla Inc",
+                        "transactionType": "SWAP",
+                        "note": "Overnight fee",
+                        "reference": "0004",
+                        "size": "-0.3",
+                        "currency": "USD",
+                        "status": "PROCESSED"
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let realized_pl = response.realized_pl_by_currency();
+        assert_eq!(realized_pl.len(), 2);
+        assert_eq!(realized_pl[&enums::Currency::USD], 10.);
+        assert_eq!(realized_pl[&enums::Currency::EUR], 4.);
+        assert_eq!(response.transactions[3].profit_and_loss(), None);
+    }
+
+    #[test]
+    fn partial_fills() {
+        let confirmation = |affected_deals: &str| {
+            serde_json::from_str::<OrderConfirmationResponse>(&format!(
+                r#"{{
+                    "date": "2024-06-11T13:00:00.000",
+                    "status": "OPEN",
+                    "dealStatus": "ACCEPTED",
+                    "epic": "TSLA",
+                    "dealReference": "o_0001",
+                    "dealId": "0001",
+                    "affectedDeals": [{affected_deals}],
+                    "level": 170.0,
+                    "size": 10,
+                    "direction": "BUY",
+                    "guaranteedStop": false,
+                    "trailingStop": false
+                }}"#
+            ))
+            .unwrap()
+        };
+
+        let filled = confirmation(
+            r#"{"dealId": "0001", "status": "OPENED", "size": 6, "level": 170.0},
+            {"dealId": "0002", "status": "OPENED", "size": 4, "level": 172.5},
+            {"dealId": "0003", "status": "DELETED", "size": 5, "level": 180.0}"#,
+        );
+        assert_eq!(filled.total_filled_size(), 10.);
+        assert_eq!(filled.average_fill_level(), Some(171.));
+
+        // Without sizes in the affected deals
+        let filled = confirmation(r#"{"dealId": "0001", "status": "OPENED"}"#);
+        assert_eq!(filled.total_filled_size(), 10.);
+        assert_eq!(filled.average_fill_level(), Some(170.));
+
+        let mut rejected = confirmation("");
+        rejected.deal_status = DealStatus::REJECTED;
+        assert_eq!(rejected.total_filled_size(), 0.);
+        assert_eq!(rejected.average_fill_level(), None);
+    }
+
+    #[test]
+    fn position_diff() {
+        let with_deal_id = |deal_id: &str, size: f64| {
+            let mut position = position("BUY", size, 100.);
+            position.position.deal_id = deal_id.to_string();
+            position
+        };
+
+        let old = AllPositionsResponse {
+            positions: vec![
+                with_deal_id("1", 1.),
+                with_deal_id("2", 1.),
+                with_deal_id("3", 1.),
+            ],
+        };
+        let mut new = AllPositionsResponse {
+            positions: vec![
+                with_deal_id("2", 1.),
+                with_deal_id("3", 2.),
+                with_deal_id("4", 1.),
+            ],
+        };
+        // upl changes on every tick and is no change of the position
+        new.positions[0].position.upl = 5.;
+
+        let position_diff = diff_positions(&old, &new);
+        assert_eq!(position_diff.opened, vec![with_deal_id("4", 1.)]);
+        assert_eq!(position_diff.closed, vec![with_deal_id("1", 1.)]);
+        assert_eq!(
+            position_diff.changed,
+            vec![(with_deal_id("3", 1.), with_deal_id("3", 2.))]
+        );
+
+        assert_eq!(diff_positions(&old, &old), PositionDiff::default());
+    }
+
+    #[test]
+    fn position_expiry() {
+        let position = position("BUY", 1., 1.);
+        assert_eq!(position.market.expiry, enums::Expiry::None);
+    }
+
+    #[test]
+    fn distance_to_stop() {
+        let mut long = position("BUY", 1., 170.);
+        assert_eq!(long.distance_to_stop(175., 175.5), None);
+
+        long.position.stop_level = Some(165.);
+        assert_eq!(long.distance_to_stop(175., 175.5), Some(10.));
+        assert_eq!(long.distance_to_stop(160., 160.5), Some(-5.));
+        assert_eq!(long.distance_to_stop_in_pips(175., 175.5, 2), Some(1000.));
+
+        let mut short = position("SELL", 1., 170.);
+        short.position.stop_level = Some(180.);
+        assert_eq!(short.distance_to_stop(175., 175.5), Some(4.5));
+    }
+
+    #[test]
+    fn margin() {
+        let mut position = position("BUY", 10., 170.);
+        position.position.leverage = 5;
+        assert_eq!(position.margin(), 340.);
+
+        position.position.leverage = 0;
+        assert_eq!(position.margin(), 1700.);
+    }
+
+    #[test]
+    fn notional_value() {
+        let mut position = position("BUY", 10., 170.);
+        assert_eq!(position.notional_value(), 1700.);
+
+        position.position.contract_size = 100;
+        position.market.scaling_factor = 10.;
+        assert_eq!(position.notional_value(), 17000.);
+    }
+
+    #[test]
+    fn risk() {
+        let mut position = position("SELL", 10., 170.);
+        position.position.stop_level = Some(180.);
+        position.market.offer = 172.5;
+
+        let risk = position.risk();
+        assert_eq!(risk.notional_value, 1700.);
+        assert_eq!(risk.margin, 340.);
+        assert_eq!(risk.distance_to_stop, Some(7.5));
+        assert_eq!(risk.position, position);
+    }
+
+    #[test]
+    fn exposure_in() {
+        let position = position("SELL", 2., 100.);
+        let mut rates = HashMap::new();
+
+        assert_eq!(
+            position.exposure_in(enums::Currency::USD, &rates),
+            Some(200.)
+        );
+        assert_eq!(position.exposure_in(enums::Currency::EUR, &rates), None);
+
+        rates.insert(enums::Currency::USD, 0.5);
+        assert_eq!(
+            position.exposure_in(enums::Currency::EUR, &rates),
+            Some(100.)
+        );
+    }
+
+    #[test]
+    fn opening_hours_ranges() {
+        let opening_hours: OpeningHours = serde_json::from_str(
+            r#"{
+                "mon": ["00:00 - 21:00", "21:05 - 00:00"],
+                "tue": ["13:30 - 20:00"],
+                "wed": [],
+                "thu": [],
+                "fri": [],
+                "sat": [],
+                "sun": [],
+                "zone": "-04:00"
+            }"#,
+        )
+        .unwrap();
+        let utc = |time: &str| DateTime::parse_from_rfc3339(time).unwrap().to_utc();
+
+        // 2024-06-10 is a monday
+        let monday = NaiveDate::from_ymd_opt(2024, 6, 10).unwrap();
+        assert_eq!(
+            opening_hours.ranges_on(monday).unwrap(),
+            vec![
+                (utc("2024-06-10T04:00:00Z"), utc("2024-06-11T01:00:00Z")),
+                (utc("2024-06-11T01:05:00Z"), utc("2024-06-11T04:00:00Z")),
+            ]
+        );
+        assert_eq!(
+            opening_hours.ranges_on(monday.succ_opt().unwrap()).unwrap(),
+            vec![(utc("2024-06-11T17:30:00Z"), utc("2024-06-12T00:00:00Z"))]
+        );
+        assert!(opening_hours
+            .ranges_on(NaiveDate::from_ymd_opt(2024, 6, 12).unwrap())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn open_ranges() {
+        let opening_hours: OpeningHours = serde_json::from_str(
+            r#"{
+                "mon": ["13:30 - 20:00", "22:00 - 00:00"],
+                "tue": ["00:00 - 20:00"],
+                "wed": [],
+                "thu": [],
+                "fri": [],
+                "sat": [],
+                "sun": ["22:00 - 00:00"],
+                "zone": "UTC"
+            }"#,
+        )
+        .unwrap();
+        let utc = |time: &str| DateTime::parse_from_rfc3339(time).unwrap().to_utc();
+
+        // 2024-06-10 is a monday
+        assert_eq!(
+            opening_hours
+                .open_ranges(utc("2024-06-09T23:00:00Z"), utc("2024-06-12T00:00:00Z"))
+                .unwrap(),
+            vec![
+                (utc("2024-06-09T23:00:00Z"), utc("2024-06-10T00:00:00Z")),
+                (utc("2024-06-10T13:30:00Z"), utc("2024-06-10T20:00:00Z")),
+                (utc("2024-06-10T22:00:00Z"), utc("2024-06-11T20:00:00Z")),
+            ]
+        );
+        assert!(opening_hours
+            .open_ranges(utc("2024-06-12T00:00:00Z"), utc("2024-06-16T00:00:00Z"))
+            .unwrap()
+            .is_empty());
+
+        let named_zone = r#"{"mon": [], "tue": [], "wed": [], "thu": [], "fri": [], "sat": [], "sun": [], "zone": "America/New_York"}"#;
+        let opening_hours: OpeningHours = serde_json::from_str(named_zone).unwrap();
+        assert!(opening_hours
+            .open_ranges(utc("2024-06-10T00:00:00Z"), utc("2024-06-11T00:00:00Z"))
+            .is_none());
+    }
+
+    #[test]
+    fn local_time() {
+        let timezone = timezone_from_offset(2);
+        assert_eq!(
+            parse_local_time("2024-06-11T15:00:00.000", timezone),
+            DateTime::from_timestamp(1718110800, 0)
+        );
+        assert_eq!(
+            parse_local_time("2024-06-11T15:00:00", timezone),
+            DateTime::from_timestamp(1718110800, 0)
+        );
+        assert_eq!(parse_local_time("11.06.2024 15:00", timezone), None);
+    }
 }
@@ -19,6 +19,14 @@ pub trait ReqwestUtils {
             Err(e) => return Err(CapitalDotComError::ReqwestError(e)),
         };
 
+        if status_code == 401 {
+            return Err(CapitalDotComError::Unauthorized);
+        }
+
+        if status_code == 403 {
+            return Err(CapitalDotComError::Forbidden(body_raw));
+        }
+
         if status_code == 200 {
             // json to rust struct
             match serde_json::from_str(&body_raw) {
@@ -26,9 +34,13 @@ pub trait ReqwestUtils {
                 Err(e) => return Err(CapitalDotComError::JsonError(e)),
             }
         } else {
+            // Proxies answer e.g. 502 and 503 with HTML pages, keep the status code so these stay transient
+            let api_error = Self::get_value_from_json(&body_raw)
+                .unwrap_or_else(|_| responses::APIError::from_raw_body(&body_raw));
+
             return Err(CapitalDotComError::StatusCode(
                 status_code,
-                Self::get_value_from_json(&body_raw)?,
+                api_error,
                 body_raw,
             ));
         }
@@ -69,32 +81,112 @@ pub trait ReqwestUtils {
     }
 }
 
+/// Pacing:
+///  * Methods that send a single request do not wait, spacing calls one after another is up to the caller
+///  * Methods that send several requests (e.g. place_bracket_order, reverse_position, close_all_positions)
+///    throttle between their own requests, 100 ms before every order
 pub trait CapitalDotComInterface {
-    /// Start a new session and connect to the Capital.com API
+    /// Start a new session and connect to the Capital.com API.
+    /// Needs exclusive access because it sets the active account.
+    /// Fails with CapitalDotComError::NoAccountsAvailable if the API key has no accounts.
     fn open_session(&mut self) -> Result<responses::CreateNewSessionResponse, CapitalDotComError>;
 
+    /// Get the session details and update the cached account id, client id, currency, time zone and streaming host.
+    /// Called by switch_account, the cached values of open_session belong to the previous account.
+    fn refresh_session_details(
+        &mut self,
+    ) -> Result<responses::SessionDetailsResponse, CapitalDotComError>;
+
     /// Get informations about the current account
     fn get_session_details(&self) -> Result<responses::SessionDetailsResponse, CapitalDotComError>;
 
     fn get_balance(&self) -> Result<responses::BalanceAccountInfo, CapitalDotComError>;
 
+    /// Fetch the balance and return it if it differs from previous, None if nothing changed.
+    /// For polling loops until streaming account updates are supported.
+    fn balance_changed_since(
+        &self,
+        previous: &responses::BalanceAccountInfo,
+    ) -> Result<Option<responses::BalanceAccountInfo>, CapitalDotComError>;
+
     fn get_all_accounts(&self) -> Result<responses::AllAccountsResponse, CapitalDotComError>;
 
-    /// Switch the trading account
+    /// Distinct currencies of all accounts, sorted.
+    fn account_currencies(&self) -> Result<Vec<enums::Currency>, CapitalDotComError>;
+
+    /// Balance, open P/L, number of positions and estimated margin of the current account in one call.
+    /// Makes two requests (balance and positions).
+    fn account_summary(&self) -> Result<responses::AccountSummary, CapitalDotComError>;
+
+    /// Open positions, working orders and the activity of the last day in one call, to rebuild the local state after a restart.
+    /// Makes three requests, spaced like the other composed calls.
+    fn reconcile(&self) -> Result<responses::ReconciliationSnapshot, CapitalDotComError>;
+
+    /// Switch the trading account.
+    /// Needs exclusive access because it changes the active account for all following calls.
+    /// Returns once the session details report the new account as active,
+    /// otherwise CapitalDotComError::AccountSwitchNotConfirmed with the active account.
+    /// If only the session details can not be fetched their error is returned, but the account is switched already.
+    /// If the new account has dealing disabled, order placement returns CapitalDotComError::DealingDisabled.
     fn switch_account(
         &mut self,
         account_id: &str,
     ) -> Result<responses::SwitchAccountResponse, CapitalDotComError>;
 
+    /// Preferences (hedging mode and leverages) of the current account.
+    /// The response is cached for get_leverage_for.
+    fn get_account_preferences(
+        &self,
+    ) -> Result<responses::AccountPreferencesResponse, CapitalDotComError>;
+
+    /// Leverage of the instrument group (see InstrumentType::leverage_group) from the cached preferences.
+    /// None if get_account_preferences was not called for the current account or the group is unknown.
+    fn get_leverage_for(&self, instrument_group: &str) -> Option<responses::Leverage>;
+
     /// Log out of the session
     fn close_session(&self) -> Result<responses::SessionLogOutResponse, CapitalDotComError>;
 
+    /// Search markets by search term and/or up to 50 epics.
+    /// Leave search_term None to only query by epics.
     fn search_market(
         &self,
-        search_term: &str,
+        search_term: Option<&str>,
         epic: Vec<String>,
     ) -> Result<responses::MarketDetailsResponse, CapitalDotComError>;
 
+    /// Like search_market but without the limit of 50 epics.
+    /// The epics are split into chunks of 50 that are requested one after another and merged into one response.
+    fn search_market_chunked(
+        &self,
+        search_term: Option<&str>,
+        epics: Vec<String>,
+    ) -> Result<responses::MarketDetailsResponse, CapitalDotComError>;
+
+    /// Top level nodes of the market navigation if node_id is None, else the sub nodes and markets of the node.
+    fn get_market_navigation(
+        &self,
+        node_id: Option<&str>,
+    ) -> Result<responses::MarketNavigationResponse, CapitalDotComError>;
+
+    /// All tradeable markets found by walking the whole market navigation.
+    /// Walking the navigation takes one request per node, so the result is cached for an hour.
+    fn list_tradeable_markets(&self) -> Result<Vec<responses::Market>, CapitalDotComError>;
+
+    /// Tradeable markets of one instrument type (e.g. all shares), filtered from list_tradeable_markets.
+    fn search_markets_by_type(
+        &self,
+        instrument_type: enums::InstrumentType,
+    ) -> Result<Vec<responses::Market>, CapitalDotComError>;
+
+    /// Margin per unit of the market at the current price and the largest size the available balance
+    /// of the current account can afford (see SingleMarketDetailsResponse::margin_info).
+    /// The balance is used without currency conversion, so max_size assumes the account and the instrument
+    /// share a currency. Makes two requests (market details and balance).
+    fn current_margin_requirement(
+        &self,
+        epic: &str,
+    ) -> Result<responses::MarginInfo, CapitalDotComError>;
+
     /// Get current bid and ask prices and other market data
     fn get_market_data(
         &self,
@@ -103,21 +195,246 @@ pub trait CapitalDotComInterface {
 
     fn get_all_positions(&self) -> Result<responses::AllPositionsResponse, CapitalDotComError>;
 
+    /// All open positions with notional value, margin and distance to stop (see PositionResponse::risk).
+    /// Uses the market data embedded in the positions, so it costs a single request.
+    fn positions_with_risk(&self) -> Result<Vec<responses::PositionRisk>, CapitalDotComError>;
+
+    /// Open a position and wait up to 5 seconds for its final confirmation (submit_position and PendingOrder::confirm).
+    /// If the confirmation fails CapitalDotComError::ConfirmationFailed carries the deal_reference.
+    /// A rejected order returns CapitalDotComError::OrderRejected with the reject reason.
     fn open_position(
         &self,
         position_data: request_bodies::CreatePositionBody,
     ) -> Result<responses::OrderConfirmationResponse, CapitalDotComError>;
 
+    /// Same as open_position, but returns the deal reference of the accepted order next to the confirmation.
+    /// The errors after the order was accepted (ConfirmationFailed, OrderRejected) carry the deal_reference as well.
+    fn open_position_detailed(
+        &self,
+        position_data: request_bodies::CreatePositionBody,
+    ) -> Result<
+        (
+            responses::DealReferenceResponse,
+            responses::OrderConfirmationResponse,
+        ),
+        CapitalDotComError,
+    >;
+
+    /// First half of open_position: send the position without waiting for the confirmation.
+    /// The PendingOrder only has the deal_reference, PendingOrder::confirm yields the ConfirmedOrder with the deal_id.
+    fn submit_position(
+        &self,
+        position_data: request_bodies::CreatePositionBody,
+    ) -> Result<PendingOrder, CapitalDotComError>;
+
+    /// Body for the smallest position the market allows (min_deal_size of the dealing rules), e.g. for test orders.
+    fn min_order_body(
+        &self,
+        epic: &str,
+        direction: enums::Direction,
+    ) -> Result<request_bodies::CreatePositionBody, CapitalDotComError>;
+
+    /// Open a position with a stop loss and a take profit.
+    /// The distances get checked against the dealing rules of the market,
+    /// limits given as a percentage at the current opening price (see Snapshot::opening_price).
+    fn place_bracket_order(
+        &self,
+        direction: enums::Direction,
+        epic: &str,
+        size: f64,
+        stop_distance: f64,
+        profit_distance: f64,
+    ) -> Result<responses::OrderConfirmationResponse, CapitalDotComError>;
+
+    /// Open a position sized by risk.
+    /// The size is chosen so that hitting the stop loses risk_fraction of the available balance
+    /// (see SingleMarketDetailsResponse::value_per_point). The stop distance is checked like in place_bracket_order.
+    /// Returns the computed size and the confirmation.
+    fn open_position_risk_based(
+        &self,
+        direction: enums::Direction,
+        epic: &str,
+        risk_fraction: f64,
+        stop_distance: f64,
+    ) -> Result<(f64, responses::OrderConfirmationResponse), CapitalDotComError>;
+
+    /// Open a position and retry if the request failed on the way (see CapitalDotComError::is_transient).
+    /// Before every retry the open positions are checked for a position with the same epic, direction and size
+    /// that was created after the first attempt. If one is found CapitalDotComError::PositionAlreadyOpened is returned
+    /// instead of opening a second position.
+    fn open_position_with_retries(
+        &self,
+        position_data: request_bodies::CreatePositionBody,
+        retries: u32,
+    ) -> Result<responses::OrderConfirmationResponse, CapitalDotComError>;
+
+    /// Poll the confirmation until it is final (not pending and available) or the timeout expires.
+    /// Polls every 200 ms to stay within the rate limits.
+    fn await_confirmation(
+        &self,
+        deal_reference: &str,
+        timeout: std::time::Duration,
+    ) -> Result<responses::OrderConfirmationResponse, CapitalDotComError>;
+
+    /// Check if an order was accepted
+    fn order_confirmation(
+        &self,
+        deal_reference: &str,
+    ) -> Result<responses::OrderConfirmationResponse, CapitalDotComError>;
+
+    /// The open position with the deal_id of the position (e.g. ConfirmedOrder::deal_id).
+    /// A position that is closed or does not exist is answered with 404.
     fn get_position_data(
         &self,
         deal_id: &str,
     ) -> Result<responses::PositionResponse, CapitalDotComError>;
 
+    /// Whether new positions can be opened in the market right now, see Snapshot::is_tradeable.
+    fn is_epic_tradeable(&self, epic: &str) -> Result<bool, CapitalDotComError>;
+
+    /// The position and a fresh snapshot of its market, for P/L and stop distances at current prices.
+    /// The market embedded in the position can be older than the single market details.
+    /// Costs a second request (and a second slot of the rate limit) compared to get_position_data.
+    fn position_with_fresh_market(
+        &self,
+        deal_id: &str,
+    ) -> Result<
+        (
+            responses::PositionResponse,
+            responses::SingleMarketDetailsResponse,
+        ),
+        CapitalDotComError,
+    >;
+
+    /// Update the stop and profit of a position (e.g. from get_all_positions).
+    /// Returns CapitalDotComError::NoChanges without sending a request if the update would not change
+    /// the position (see PositionUpdateBody::changes), so stops can be pushed every tick without using the rate limit.
+    fn update_position(
+        &self,
+        position: &responses::PositionResponse,
+        position_update_data: request_bodies::PositionUpdateBody,
+    ) -> Result<responses::DealReferenceResponse, CapitalDotComError>;
+
     fn close_position(
         &self,
         deal_id: &str,
     ) -> Result<responses::DealReferenceResponse, CapitalDotComError>;
 
+    /// Close a position with an order in the opposite direction, see ClosePositionBody.
+    /// A market close is sent as a position (open_position), a LIMIT or STOP close as a working order at the level.
+    /// Both wait for the confirmation like open_position.
+    /// This only closes the position while the hedging mode of the account is off, otherwise
+    /// CapitalDotComError::HedgingModeEnabled is returned. Fetches the position and the account preferences first.
+    fn close_position_with(
+        &self,
+        deal_id: &str,
+        close_position_data: request_bodies::ClosePositionBody,
+    ) -> Result<responses::OrderConfirmationResponse, CapitalDotComError>;
+
+    /// Close the position, wait for the confirmation and check that the position is gone.
+    /// A transient error of the close request is not returned directly, the check decides.
+    /// Fails with CapitalDotComError::PositionStillOpen if the position still exists after the close.
+    fn close_position_confirmed(&self, deal_id: &str) -> Result<(), CapitalDotComError>;
+
+    /// Close all open positions one after another.
+    /// Returns the result per deal_id, a failed close does not stop the others.
+    fn close_all_positions(&self) -> Result<DealResults, CapitalDotComError>;
+
+    /// Open a position with the same epic and size in the opposite direction and keep the position open.
+    /// Only possible in hedging mode, otherwise CapitalDotComError::HedgingModeDisabled is returned
+    /// because the new position would close the existing one. Fetches the position and the account preferences first.
+    fn hedge_position(
+        &self,
+        deal_id: &str,
+    ) -> Result<responses::OrderConfirmationResponse, CapitalDotComError>;
+
+    /// Open additional_size more in the direction of the position (scale in), the counterpart of a partial close.
+    /// On a netting account the new deal nets with the position, in hedging mode it is a separate position.
+    /// additional_size is checked against min_deal_size and max_deal_size of the market before the order is sent.
+    fn add_to_position(
+        &self,
+        deal_id: &str,
+        additional_size: f64,
+    ) -> Result<responses::OrderConfirmationResponse, CapitalDotComError>;
+
+    /// Close the position and open a position with the same size in the opposite direction.
+    /// The close goes through close_position_confirmed, the new position is only opened once the old one is gone.
+    fn reverse_position(
+        &self,
+        deal_id: &str,
+    ) -> Result<responses::OrderConfirmationResponse, CapitalDotComError>;
+
+    fn get_all_working_orders(
+        &self,
+    ) -> Result<responses::AllWorkingOrdersResponse, CapitalDotComError>;
+
+    /// Get a single working order.
+    /// The API has no endpoint for a single working order, so all working orders are fetched and filtered by the deal_id.
+    fn get_working_order(
+        &self,
+        deal_id: &str,
+    ) -> Result<responses::WorkingOrder, CapitalDotComError>;
+
+    fn delete_working_order(
+        &self,
+        deal_id: &str,
+    ) -> Result<responses::DealReferenceResponse, CapitalDotComError>;
+
+    /// Delete all working orders one after another.
+    /// Returns the result per deal_id, a failed order does not stop the others.
+    fn cancel_all_working_orders(&self) -> Result<DealResults, CapitalDotComError>;
+
+    /// Detailed account activity (position, order and system events) between from and to
+    fn get_activity_history(
+        &self,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+    ) -> Result<responses::ActivityHistoryResponse, CapitalDotComError>;
+
+    /// Activities that opened a position between from and to
+    fn positions_opened_between(
+        &self,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<responses::Activity>, CapitalDotComError>;
+
+    /// Transactions between from and to, optionally of one type only
+    fn get_transaction_history(
+        &self,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+        transaction_type: Option<enums::TransactionType>,
+    ) -> Result<responses::TransactionHistoryResponse, CapitalDotComError>;
+
+    /// Realized profit/loss of the trades closed between from and to in the given currency.
+    /// rates maps a currency to the rate for converting one unit of it into `currency`,
+    /// fails with CapitalDotComError::MissingExchangeRate if a trade is in a currency without rate.
+    /// Use TransactionHistoryResponse::realized_pl_by_currency for the P/L per currency.
+    fn realized_pl_between(
+        &self,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+        currency: enums::Currency,
+        rates: &HashMap<enums::Currency, f64>,
+    ) -> Result<f64, CapitalDotComError>;
+
+    /// Current time of the server
+    fn get_server_time(&self) -> Result<responses::ServerTimeResponse, CapitalDotComError>;
+
+    /// Current time of the server as a DateTime, e.g. for aligning price windows to the clock of the broker
+    fn server_time(&self) -> Result<chrono::DateTime<chrono::Utc>, CapitalDotComError>;
+
+    /// The last last_n_bars (max 1000) candles up to the current server time.
+    /// The time window is widened until enough candles are found, so weekends and holidays are skipped.
+    /// Returns fewer candles if the market has no older prices.
+    /// A window without any prices (404) is widened as well, only the 404 of the widest window is returned.
+    fn get_recent_prices(
+        &self,
+        epic: &str,
+        resolution: enums::Resolution,
+        last_n_bars: usize,
+    ) -> Result<responses::HistoricalPricesResponse, CapitalDotComError>;
+
     fn get_historical_prices(
         &self,
         epic: &str,
@@ -126,6 +443,29 @@ pub trait CapitalDotComInterface {
         from: chrono::DateTime<chrono::Utc>,
         to: chrono::DateTime<chrono::Utc>,
     ) -> Result<responses::HistoricalPricesResponse, CapitalDotComError>;
+
+    /// All candles between from and to, requested in windows of up to 1000 candles.
+    /// For resolutions below a day only the open sessions from the opening hours of the market are requested
+    /// (one extra request for the market details), so weekends and closed hours cost no requests.
+    /// Falls back to continuous windows if the opening hours can not be converted (see OpeningHours::open_ranges).
+    /// Holidays are not part of the opening hours, their windows are requested and come back empty.
+    fn get_historical_prices_paginated(
+        &self,
+        epic: &str,
+        resolution: enums::Resolution,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<responses::Prices>, CapitalDotComError>;
+
+    /// Mid price candles between from and to, sorted by time (get_historical_prices_paginated and Candle::from_prices).
+    /// Prices with a snapshot_time_UTC that can not be parsed are left out.
+    fn get_candles(
+        &self,
+        epic: &str,
+        resolution: enums::Resolution,
+        from: chrono::DateTime<chrono::Utc>,
+        to: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<candle::Candle>, CapitalDotComError>;
 }
 
 pub trait CapitalDotComEndpoints: ReqwestUtils {
@@ -153,11 +493,22 @@ pub trait CapitalDotComEndpoints: ReqwestUtils {
         &mut self,
     ) -> Result<(HashMap<String, String>, responses::AllAccountsResponse), CapitalDotComError>;
 
+    /// Switch the active account. Session tokens rotated in the response headers replace the current ones.
     async fn switch_active_account(
         &mut self,
         account_id: &str,
     ) -> Result<(HashMap<String, String>, responses::SwitchAccountResponse), CapitalDotComError>;
 
+    async fn get_account_preferences(
+        &mut self,
+    ) -> Result<
+        (
+            HashMap<String, String>,
+            responses::AccountPreferencesResponse,
+        ),
+        CapitalDotComError,
+    >;
+
     async fn session_log_out(
         &mut self,
     ) -> Result<(HashMap<String, String>, responses::SessionLogOutResponse), CapitalDotComError>;
@@ -183,6 +534,12 @@ pub trait CapitalDotComEndpoints: ReqwestUtils {
         position_data: request_bodies::CreatePositionBody,
     ) -> Result<(HashMap<String, String>, responses::DealReferenceResponse), CapitalDotComError>;
 
+    /// Create a LIMIT or STOP order.
+    async fn create_working_order(
+        &mut self,
+        working_order_data: request_bodies::CreateWorkingOrderBody,
+    ) -> Result<(HashMap<String, String>, responses::DealReferenceResponse), CapitalDotComError>;
+
     async fn get_position(
         &mut self,
         deal_id: String,
@@ -199,9 +556,45 @@ pub trait CapitalDotComEndpoints: ReqwestUtils {
         deal_id: String,
     ) -> Result<(HashMap<String, String>, responses::DealReferenceResponse), CapitalDotComError>;
 
+    async fn get_all_working_orders(
+        &mut self,
+    ) -> Result<(HashMap<String, String>, responses::AllWorkingOrdersResponse), CapitalDotComError>;
+
+    async fn delete_working_order(
+        &mut self,
+        deal_id: &str,
+    ) -> Result<(HashMap<String, String>, responses::DealReferenceResponse), CapitalDotComError>;
+
+    /// Detailed account activity between from and to.
+    async fn get_activity_history(
+        &mut self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<(HashMap<String, String>, responses::ActivityHistoryResponse), CapitalDotComError>;
+
+    /// Transactions (trades, swaps, deposits, ...) between from and to, optionally of one type only.
+    async fn get_transaction_history(
+        &mut self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        transaction_type: Option<enums::TransactionType>,
+    ) -> Result<
+        (
+            HashMap<String, String>,
+            responses::TransactionHistoryResponse,
+        ),
+        CapitalDotComError,
+    >;
+
+    /// Top level nodes if node_id is None, else the sub nodes and markets of the node.
+    async fn get_market_navigation(
+        &mut self,
+        node_id: Option<&str>,
+    ) -> Result<(HashMap<String, String>, responses::MarketNavigationResponse), CapitalDotComError>;
+
     async fn get_market_details(
         &mut self,
-        search_term: &str,
+        search_term: Option<&str>,
         epics: Vec<String>,
     ) -> Result<(HashMap<String, String>, responses::MarketDetailsResponse), CapitalDotComError>;
 
@@ -228,10 +621,55 @@ pub trait CapitalDotComEndpoints: ReqwestUtils {
         to: DateTime<Utc>,
     ) -> Result<(HashMap<String, String>, responses::HistoricalPricesResponse), CapitalDotComError>;
 
+    /// Authenticated request to any path, the body is returned as untyped json.
+    async fn request_raw(
+        &mut self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<serde_json::Value>,
+    ) -> Result<(HashMap<String, String>, serde_json::Value), CapitalDotComError>;
+
+    /// Authenticated request to any path, the body is deserialized into T.
+    async fn request_typed<T: for<'a> Deserialize<'a>, B: Serialize>(
+        &mut self,
+        method: reqwest::Method,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Option<B>,
+    ) -> Result<(HashMap<String, String>, T), CapitalDotComError>;
+
     fn has_credentials(&self) -> Result<(), CapitalDotComError>;
 
-    /// Unwrap the response of the API to the status code, headers and the body that will be casted into the fitting response struct.
+    /// Store the rate limit state of the last response.
+    fn set_rate_limit_status(&mut self, rate_limit_status: endpoint::RateLimitStatus);
+
+    /// Send the request and retry it on transient errors as configured by retry.
     async fn request_data<T: for<'a> Deserialize<'a>>(
+        &mut self,
+        request_builder: RequestBuilder,
+        retry: endpoint::Retry,
+    ) -> Result<(HashMap<String, String>, T), CapitalDotComError> {
+        let mut attempt = 0;
+        loop {
+            let request = match request_builder.try_clone() {
+                Some(request) if attempt < retry.retries => request,
+                _ => return self.send_request(request_builder).await,
+            };
+
+            match self.send_request(request).await {
+                Err(e) if e.is_transient() => {
+                    attempt += 1;
+                    tokio::time::sleep(retry.delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Unwrap the response of the API to the status code, headers and the body that will be casted into the fitting response struct.
+    /// Updates the rate limit state, also for error responses.
+    async fn send_request<T: for<'a> Deserialize<'a>>(
+        &mut self,
         request_builder: RequestBuilder,
     ) -> Result<(HashMap<String, String>, T), CapitalDotComError> {
         let response = match request_builder.send().await {
@@ -239,6 +677,14 @@ pub trait CapitalDotComEndpoints: ReqwestUtils {
             Err(e) => return Err(CapitalDotComError::ReqwestError(e)),
         };
 
+        if let Some(rate_limit_status) = endpoint::RateLimitStatus::from_headers(
+            response.headers(),
+            response.status().as_u16(),
+            Utc::now(),
+        ) {
+            self.set_rate_limit_status(rate_limit_status);
+        }
+
         let headers = Self::headers_to_hashmap(response.headers().to_owned());
         let body = Self::get_body(response).await?;
 
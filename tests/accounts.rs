@@ -0,0 +1,360 @@
+mod mock_server;
+
+use std::collections::HashMap;
+
+use capitaldotcom_api::{
+    request_bodies::CreatePositionBodyBuilder, CapitalDotComAPIBuilder, CapitalDotComError,
+    CapitalDotComInterface, Currency, Direction, InstrumentType, RateLimitBehavior, SessionType,
+};
+use chrono::DateTime;
+use mock_server::{
+    login_response, MockResponse, MockServer, ACCOUNT_PREFERENCES_JSON, ALL_ACCOUNTS_JSON,
+};
+
+const SWITCH_ACCOUNT_JSON: &str = r#"{
+    "trailingStopsEnabled": false,
+    "dealingEnabled": true,
+    "hasActiveDemoAccounts": true,
+    "hasActiveLiveAccounts": false
+}"#;
+
+fn session_details_json(account_id: &str) -> String {
+    format!(
+        r#"{{
+            "clientId": "12345678",
+            "accountId": "{account_id}",
+            "timezoneOffset": 1,
+            "locale": "en",
+            "currency": "EUR",
+            "streamEndpoint": "wss://api-streaming-capital.backend-capital.com/"
+        }}"#
+    )
+}
+
+#[test]
+fn leverage_from_cached_preferences() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, ACCOUNT_PREFERENCES_JSON),
+        MockResponse::json(200, SWITCH_ACCOUNT_JSON),
+        MockResponse::json(200, &session_details_json("0002")),
+    ]);
+    let mut capital_api = CapitalDotComAPIBuilder::new(
+        SessionType::Demo,
+        String::from("api_key"),
+        String::from("user@example.com"),
+        String::from("password"),
+    )
+    .base_url(&mock_server.url)
+    .build();
+    capital_api.open_session().unwrap();
+    assert_eq!(capital_api.session_currency(), Some(Currency::USD));
+
+    // Nothing cached yet
+    assert!(capital_api.get_leverage_for("SHARES").is_none());
+
+    let preferences = capital_api.get_account_preferences().unwrap();
+    assert!(!preferences.hedging_mode);
+
+    let leverage = capital_api
+        .get_leverage_for(InstrumentType::SHARES.leverage_group())
+        .unwrap();
+    assert_eq!(leverage.current, 5);
+    assert_eq!(leverage.available, vec![1, 2, 3, 4, 5]);
+    assert!(capital_api.get_leverage_for("UNKNOWN").is_none());
+
+    // The preferences of the previous account are dropped
+    capital_api.switch_account("0002").unwrap();
+    assert!(capital_api.get_leverage_for("SHARES").is_none());
+
+    // The session of the new account is cached
+    assert_eq!(capital_api.session_currency(), Some(Currency::EUR));
+    assert_eq!(capital_api.session_timezone().local_minus_utc(), 3600);
+
+    let requests = mock_server.requests();
+    assert_eq!(requests[1].path, "/api/v1/accounts/preferences");
+}
+
+#[test]
+fn switch_account_is_confirmed() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, SWITCH_ACCOUNT_JSON),
+        MockResponse::json(200, &session_details_json("0001")),
+    ]);
+    let mut capital_api = CapitalDotComAPIBuilder::new(
+        SessionType::Demo,
+        String::from("api_key"),
+        String::from("user@example.com"),
+        String::from("password"),
+    )
+    .base_url(&mock_server.url)
+    .build();
+    capital_api.open_session().unwrap();
+
+    match capital_api.switch_account("0002") {
+        Err(CapitalDotComError::AccountSwitchNotConfirmed(account_id)) => {
+            assert_eq!(account_id, "0001")
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+
+    let requests = mock_server.requests();
+    assert_eq!(requests[1].method, "PUT");
+    assert_eq!(requests[2].path, "/api/v1/session");
+}
+
+#[test]
+fn switch_account_uses_rotated_tokens() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, SWITCH_ACCOUNT_JSON)
+            .header("CST", "new_cst_token")
+            .header("X-SECURITY-TOKEN", "new_security_token"),
+        MockResponse::json(200, &session_details_json("0002")),
+        MockResponse::json(200, SWITCH_ACCOUNT_JSON),
+        MockResponse::json(200, &session_details_json("0001")),
+    ]);
+    let mut capital_api = CapitalDotComAPIBuilder::new(
+        SessionType::Demo,
+        String::from("api_key"),
+        String::from("user@example.com"),
+        String::from("password"),
+    )
+    .base_url(&mock_server.url)
+    .build();
+    capital_api.open_session().unwrap();
+
+    capital_api.switch_account("0002").unwrap();
+    assert_eq!(
+        capital_api.streaming_credentials().unwrap().cst,
+        "new_cst_token"
+    );
+
+    // Without tokens in the response the current ones are kept
+    capital_api.switch_account("0001").unwrap();
+
+    let requests = mock_server.requests();
+    assert_eq!(requests[1].header("CST"), Some("cst_token"));
+    assert_eq!(requests[2].header("CST"), Some("new_cst_token"));
+    assert_eq!(
+        requests[2].header("X-SECURITY-TOKEN"),
+        Some("new_security_token")
+    );
+    assert_eq!(requests[4].header("CST"), Some("new_cst_token"));
+}
+
+#[test]
+fn switch_account_without_session_details() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, SWITCH_ACCOUNT_JSON),
+        MockResponse::json(400, r#"{"errorCode": "error.invalid.session"}"#),
+    ]);
+    let mut capital_api = CapitalDotComAPIBuilder::new(
+        SessionType::Demo,
+        String::from("api_key"),
+        String::from("user@example.com"),
+        String::from("password"),
+    )
+    .base_url(&mock_server.url)
+    .build();
+    capital_api.open_session().unwrap();
+
+    assert_eq!(
+        capital_api.switch_account("0002").unwrap_err().status(),
+        Some(400)
+    );
+
+    // The switch itself succeeded, 0002 is the active account
+    assert!(matches!(
+        capital_api.switch_account("0002"),
+        Err(CapitalDotComError::NotDifferentAccountId)
+    ));
+    assert_eq!(mock_server.requests().len(), 3);
+}
+
+#[test]
+fn switch_to_account_without_dealing() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(
+            200,
+            &SWITCH_ACCOUNT_JSON.replace(r#""dealingEnabled": true"#, r#""dealingEnabled": false"#),
+        ),
+        MockResponse::json(200, &session_details_json("0002")),
+    ]);
+    let mut capital_api = CapitalDotComAPIBuilder::new(
+        SessionType::Demo,
+        String::from("api_key"),
+        String::from("user@example.com"),
+        String::from("password"),
+    )
+    .base_url(&mock_server.url)
+    .build();
+    capital_api.open_session().unwrap();
+    assert!(capital_api.is_dealing_enabled());
+
+    capital_api.switch_account("0002").unwrap();
+    assert!(!capital_api.is_dealing_enabled());
+
+    let position = CreatePositionBodyBuilder::new(Direction::BUY, "TSLA", 1.)
+        .build()
+        .unwrap();
+    assert!(matches!(
+        capital_api.open_position(position),
+        Err(CapitalDotComError::DealingDisabled)
+    ));
+    assert_eq!(mock_server.requests().len(), 3);
+}
+
+#[test]
+fn rate_limit_status_from_headers() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, ALL_ACCOUNTS_JSON)
+            .header("X-RateLimit-Remaining", "9")
+            .header("X-RateLimit-Reset", "1"),
+    ]);
+    let mut capital_api = CapitalDotComAPIBuilder::new(
+        SessionType::Demo,
+        String::from("api_key"),
+        String::from("user@example.com"),
+        String::from("password"),
+    )
+    .base_url(&mock_server.url)
+    .build();
+    capital_api.open_session().unwrap();
+    assert!(capital_api.rate_limit_status().is_none());
+
+    capital_api.get_all_accounts().unwrap();
+    let rate_limit_status = capital_api.rate_limit_status().unwrap();
+    assert_eq!(rate_limit_status.remaining, Some(9));
+    assert!(rate_limit_status.reset_at.is_some());
+}
+
+#[test]
+fn rate_limit_behavior_fail() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, ALL_ACCOUNTS_JSON)
+            .header("X-RateLimit-Remaining", "0")
+            .header("X-RateLimit-Reset", "60"),
+    ]);
+    let mut capital_api = CapitalDotComAPIBuilder::new(
+        SessionType::Demo,
+        String::from("api_key"),
+        String::from("user@example.com"),
+        String::from("password"),
+    )
+    .base_url(&mock_server.url)
+    .rate_limit_behavior(RateLimitBehavior::Fail)
+    .build();
+    capital_api.open_session().unwrap();
+    assert!(capital_api.check_rate_limit().is_ok());
+
+    // Fails after the balance, before requesting the positions
+    match capital_api.account_summary() {
+        Err(CapitalDotComError::RequestingTooFast(wait_time)) => {
+            assert!(wait_time.num_seconds() > 50 && wait_time.num_seconds() <= 60)
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+    assert!(matches!(
+        capital_api.check_rate_limit(),
+        Err(CapitalDotComError::RequestingTooFast(_))
+    ));
+    assert_eq!(mock_server.requests().len(), 2);
+}
+
+fn trade_json(reference: &str, size: &str, currency: &str) -> String {
+    format!(
+        r#"{{
+            "date": "2024-06-11T15:00:00.000",
+            "dateUtc": "2024-06-11T13:00:00.000",
+            "instrumentName": "Tesla Inc",
+            "transactionType": "TRADE",
+            "note": "Trade closed",
+            "reference": "{reference}",
+            "size": "{size}",
+            "currency": "{currency}",
+            "status": "PROCESSED"
+        }}"#
+    )
+}
+
+#[test]
+fn realized_pl_between_converts_currencies() {
+    let transactions_json = format!(
+        r#"{{"transactions": [{}, {}]}}"#,
+        trade_json("0001", "10.0", "USD"),
+        trade_json("0002", "-4.0", "EUR")
+    );
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, &transactions_json),
+        MockResponse::json(200, &transactions_json),
+    ]);
+    let mut capital_api = CapitalDotComAPIBuilder::new(
+        SessionType::Demo,
+        String::from("api_key"),
+        String::from("user@example.com"),
+        String::from("password"),
+    )
+    .base_url(&mock_server.url)
+    .build();
+    capital_api.open_session().unwrap();
+
+    let from = DateTime::from_timestamp(1718064000, 0).unwrap();
+    let to = DateTime::from_timestamp(1718150400, 0).unwrap();
+    let mut rates = HashMap::new();
+
+    assert!(matches!(
+        capital_api.realized_pl_between(from, to, Currency::USD, &rates),
+        Err(CapitalDotComError::MissingExchangeRate(Currency::EUR))
+    ));
+
+    rates.insert(Currency::EUR, 1.5);
+    let realized_pl = capital_api
+        .realized_pl_between(from, to, Currency::USD, &rates)
+        .unwrap();
+    assert_eq!(realized_pl, 4.);
+
+    let requests = mock_server.requests();
+    assert_eq!(
+        requests[1].path,
+        "/api/v1/history/transactions?from=2024-06-11T00%3A00%3A00&to=2024-06-12T00%3A00%3A00&type=TRADE"
+    );
+}
+
+#[test]
+fn balance_changed_since() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, ALL_ACCOUNTS_JSON),
+        MockResponse::json(200, ALL_ACCOUNTS_JSON),
+        MockResponse::json(
+            200,
+            &ALL_ACCOUNTS_JSON.replace(r#""available": 1000.0"#, r#""available": 900.0"#),
+        ),
+    ]);
+    let mut capital_api = CapitalDotComAPIBuilder::new(
+        SessionType::Demo,
+        String::from("api_key"),
+        String::from("user@example.com"),
+        String::from("password"),
+    )
+    .base_url(&mock_server.url)
+    .build();
+    capital_api.open_session().unwrap();
+
+    let balance = capital_api.get_balance().unwrap();
+    assert_eq!(capital_api.balance_changed_since(&balance).unwrap(), None);
+
+    let changed = capital_api
+        .balance_changed_since(&balance)
+        .unwrap()
+        .unwrap();
+    assert_eq!(changed.available, 900.);
+    assert_eq!(changed.balance, balance.balance);
+}
@@ -0,0 +1,380 @@
+mod mock_server;
+
+use std::time::Duration;
+
+use capitaldotcom_api::{
+    CapitalConfig, CapitalDotComAPI, CapitalDotComAPIBuilder, CapitalDotComError,
+    CapitalDotComInterface, Method, Retry, RetryPolicy, SessionType,
+};
+use serde::{Deserialize, Serialize};
+
+use mock_server::{
+    login_response, MockResponse, MockServer, ALL_ACCOUNTS_JSON, CREATE_SESSION_JSON,
+};
+
+fn client(mock_server: &MockServer) -> CapitalDotComAPI {
+    CapitalDotComAPIBuilder::new(
+        SessionType::Demo,
+        String::from("api_key"),
+        String::from("user@example.com"),
+        String::from("password"),
+    )
+    .base_url(&mock_server.url)
+    .build()
+}
+
+#[test]
+fn create_session_stores_tokens() {
+    let mock_server = MockServer::start(vec![login_response()]);
+    let mut capital_api = client(&mock_server);
+    assert_eq!(capital_api.client_id(), None);
+
+    let session = capital_api.open_session().unwrap();
+    assert_eq!(session.current_account_id, "0001");
+    assert_eq!(capital_api.session_timezone().local_minus_utc(), 2 * 3600);
+    assert_eq!(capital_api.client_id(), Some("12345678"));
+
+    let streaming_credentials = capital_api.streaming_credentials().unwrap();
+    assert_eq!(streaming_credentials.cst, "cst_token");
+    assert_eq!(streaming_credentials.security_token, "security_token");
+
+    let requests = mock_server.requests();
+    assert_eq!(requests[0].method, "POST");
+    assert_eq!(requests[0].path, "/api/v1/session");
+    assert_eq!(requests[0].header("x-cap-api-key"), Some("api_key"));
+    assert!(requests[0]
+        .body
+        .contains(r#""identifier":"user@example.com""#));
+}
+
+#[test]
+fn login_without_accounts_fails() {
+    let session_json = CREATE_SESSION_JSON.replace(
+        &CREATE_SESSION_JSON[CREATE_SESSION_JSON.find(r#""accounts""#).unwrap()
+            ..CREATE_SESSION_JSON.find(r#""clientId""#).unwrap()],
+        r#""accounts": [],
+    "#,
+    );
+    let mock_server = MockServer::start(vec![MockResponse::json(200, &session_json)
+        .header("CST", "cst_token")
+        .header("X-SECURITY-TOKEN", "security_token")]);
+    let mut capital_api = client(&mock_server);
+
+    assert!(matches!(
+        capital_api.open_session(),
+        Err(CapitalDotComError::NoAccountsAvailable)
+    ));
+    assert!(matches!(
+        capital_api.get_balance(),
+        Err(CapitalDotComError::NotLoggedIn)
+    ));
+}
+
+#[test]
+fn login_without_security_token_fails() {
+    let mock_server = MockServer::start(vec![
+        MockResponse::json(200, CREATE_SESSION_JSON).header("CST", "cst_token"),
+        MockResponse::json(200, ALL_ACCOUNTS_JSON),
+    ]);
+    let mut capital_api = client(&mock_server);
+
+    assert!(matches!(
+        capital_api.open_session(),
+        Err(CapitalDotComError::LoginFailed)
+    ));
+    assert!(matches!(
+        capital_api.get_all_accounts(),
+        Err(CapitalDotComError::NotLoggedIn)
+    ));
+}
+
+#[test]
+fn auth_headers_are_sent() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, ALL_ACCOUNTS_JSON),
+    ]);
+    let mut capital_api = client(&mock_server);
+
+    capital_api.open_session().unwrap();
+    let accounts = capital_api.get_all_accounts().unwrap();
+    assert_eq!(accounts.accounts[0].account_id, "0001");
+
+    let requests = mock_server.requests();
+    assert_eq!(requests[1].path, "/api/v1/accounts");
+    assert_eq!(requests[1].header("cst"), Some("cst_token"));
+    assert_eq!(
+        requests[1].header("x-security-token"),
+        Some("security_token")
+    );
+}
+
+#[test]
+fn unauthorized() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(401, r#"{"errorCode": "error.invalid.session.token"}"#),
+    ]);
+    let mut capital_api = client(&mock_server);
+
+    capital_api.open_session().unwrap();
+    assert!(matches!(
+        capital_api.get_all_accounts(),
+        Err(CapitalDotComError::Unauthorized)
+    ));
+}
+
+#[test]
+fn forbidden() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(401, r#"{"errorCode": "error.invalid.session.token"}"#),
+        MockResponse::json(403, r#"{"errorCode": "error.forbidden.ip"}"#),
+    ]);
+    let mut capital_api = client(&mock_server);
+    capital_api.open_session().unwrap();
+
+    let error = capital_api.get_all_accounts().unwrap_err();
+    assert!(error.needs_reauthentication());
+
+    let error = capital_api.get_all_accounts().unwrap_err();
+    assert!(!error.needs_reauthentication());
+    assert_eq!(error.status(), Some(403));
+    match error {
+        CapitalDotComError::Forbidden(body) => {
+            assert_eq!(body, r#"{"errorCode": "error.forbidden.ip"}"#)
+        }
+        error => panic!("unexpected error {:?}", error),
+    }
+}
+
+#[test]
+fn html_server_error_is_retried() {
+    let html = "<html><body><h1>503 Service Temporarily Unavailable</h1></body></html>";
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(503, html),
+        MockResponse::json(200, ALL_ACCOUNTS_JSON),
+        MockResponse::json(503, html),
+        MockResponse::json(503, html),
+    ]);
+    let mut capital_api = CapitalDotComAPIBuilder::new(
+        SessionType::Demo,
+        String::from("api_key"),
+        String::from("user@example.com"),
+        String::from("password"),
+    )
+    .base_url(&mock_server.url)
+    .retry_policy(RetryPolicy {
+        read: Retry::new(1, Duration::ZERO),
+        ..RetryPolicy::default()
+    })
+    .build();
+    capital_api.open_session().unwrap();
+
+    let all_accounts = capital_api.get_all_accounts().unwrap();
+    assert_eq!(all_accounts.accounts.len(), 1);
+
+    // Without retries left the status code and the page are returned
+    let error = capital_api.get_all_accounts().unwrap_err();
+    assert!(error.is_transient());
+    assert_eq!(error.status(), Some(503));
+    assert_eq!(error.raw_body(), Some(html));
+    assert_eq!(error.api_error().unwrap().error_code, html);
+    assert_eq!(mock_server.requests().len(), 5);
+}
+
+#[test]
+fn logout() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, r#"{"status": "FAILURE"}"#),
+        MockResponse::json(200, r#"{"status": "SUCCESS"}"#),
+    ]);
+    let mut capital_api = client(&mock_server);
+
+    capital_api.open_session().unwrap();
+    assert!(matches!(
+        capital_api.close_session(),
+        Err(CapitalDotComError::LogoutFailed)
+    ));
+    assert!(capital_api.close_session().is_ok());
+    assert!(matches!(
+        capital_api.close_session(),
+        Err(CapitalDotComError::NotLoggedIn)
+    ));
+}
+
+#[test]
+fn send_authenticated_returns_raw_response() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(404, r#"{"errorCode": "error.not-found"}"#).header("X-Custom", "1"),
+    ]);
+    let mut capital_api = client(&mock_server);
+
+    let request_builder = capital_api.request_builder(Method::GET, "/api/v1/unknown");
+    assert!(matches!(
+        capital_api.send_authenticated(request_builder),
+        Err(CapitalDotComError::NotLoggedIn)
+    ));
+
+    capital_api.open_session().unwrap();
+    let request_builder = capital_api.request_builder(Method::GET, "/api/v1/unknown");
+    let response = capital_api.send_authenticated(request_builder).unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+    assert_eq!(response.headers()["x-custom"], "1");
+
+    let body = tokio::runtime::Runtime::new()
+        .unwrap()
+        .block_on(response.text())
+        .unwrap();
+    assert_eq!(body, r#"{"errorCode": "error.not-found"}"#);
+
+    let requests = mock_server.requests();
+    assert_eq!(requests[1].path, "/api/v1/unknown");
+    assert_eq!(requests[1].header("cst"), Some("cst_token"));
+}
+
+#[test]
+fn pool_settings() {
+    let mock_server = MockServer::start(vec![login_response()]);
+    let mut capital_api = CapitalDotComAPIBuilder::new(
+        SessionType::Demo,
+        String::from("api_key"),
+        String::from("user@example.com"),
+        String::from("password"),
+    )
+    .base_url(&mock_server.url)
+    .pool_max_idle_per_host(2)
+    .pool_idle_timeout(std::time::Duration::from_secs(30))
+    .build();
+
+    assert!(capital_api.open_session().is_ok());
+}
+
+#[test]
+fn client_from_config() {
+    let mock_server = MockServer::start(vec![login_response()]);
+    let config = CapitalConfig::from_json(&format!(
+        r#"{{
+            "session_type": "Demo",
+            "api_key": "config_api_key",
+            "identifier": "user@example.com",
+            "password": "password",
+            "base_url": "{}",
+            "request_timeout_ms": 5000,
+            "rate_limit_behavior": "Fail",
+            "order_retry": {{"retries": 0, "delay_ms": 0}},
+            "trading_enabled": false
+        }}"#,
+        mock_server.url
+    ))
+    .unwrap();
+    let mut capital_api = CapitalDotComAPI::from_config(config);
+    assert!(!capital_api.is_trading_enabled());
+
+    capital_api.open_session().unwrap();
+    let requests = mock_server.requests();
+    assert_eq!(requests[0].header("x-cap-api-key"), Some("config_api_key"));
+
+    // Typos are rejected instead of silently using the default
+    assert!(matches!(
+        CapitalConfig::from_json(
+            r#"{
+                "session_type": "Demo",
+                "api_key": "api_key",
+                "identifier": "user@example.com",
+                "password": "password",
+                "request_timeout": 5000
+            }"#
+        ),
+        Err(CapitalDotComError::JsonError(_))
+    ));
+}
+
+#[test]
+fn assert_session_type() {
+    let mock_server = MockServer::start(vec![login_response(), login_response()]);
+
+    let mut capital_api = client(&mock_server);
+    capital_api.open_session().unwrap();
+    assert!(capital_api.assert_session_type(SessionType::Demo).is_ok());
+    assert!(matches!(
+        capital_api.assert_session_type(SessionType::Live),
+        Err(CapitalDotComError::WrongEnvironment(_))
+    ));
+
+    // Configured for live, but the session has no active live accounts
+    let mut capital_api = CapitalDotComAPIBuilder::new(
+        SessionType::Live,
+        String::from("api_key"),
+        String::from("user@example.com"),
+        String::from("password"),
+    )
+    .base_url(&mock_server.url)
+    .build();
+    capital_api.open_session().unwrap();
+    assert!(matches!(
+        capital_api.assert_session_type(SessionType::Live),
+        Err(CapitalDotComError::WrongEnvironment(_))
+    ));
+}
+
+#[derive(Debug, Deserialize)]
+struct Watchlists {
+    watchlists: Vec<Watchlist>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Watchlist {
+    id: String,
+}
+
+#[derive(Serialize)]
+struct CreateWatchlist<'a> {
+    name: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateWatchlistResponse {
+    watchlist_id: String,
+}
+
+#[test]
+fn typed_requests() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, r#"{"watchlists": [{"id": "123", "name": "Shares"}]}"#),
+        MockResponse::json(200, r#"{"watchlistId": "124", "status": "SUCCESS"}"#),
+    ]);
+    let mut capital_api = client(&mock_server);
+    capital_api.open_session().unwrap();
+
+    let watchlists: Watchlists = capital_api
+        .get_typed("/api/v1/watchlists", &[("limit", "10")])
+        .unwrap();
+    assert_eq!(watchlists.watchlists[0].id, "123");
+
+    let response: CreateWatchlistResponse = capital_api
+        .post_typed("/api/v1/watchlists", CreateWatchlist { name: "Indices" })
+        .unwrap();
+    assert_eq!(response.watchlist_id, "124");
+
+    capital_api.set_trading_enabled(false);
+    assert!(matches!(
+        capital_api.post_typed::<CreateWatchlistResponse, _>(
+            "/api/v1/watchlists",
+            CreateWatchlist { name: "Indices" }
+        ),
+        Err(CapitalDotComError::TradingDisabled)
+    ));
+
+    let requests = mock_server.requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[1].path, "/api/v1/watchlists?limit=10");
+    assert_eq!(requests[1].header("cst"), Some("cst_token"));
+    assert_eq!(requests[2].method, "POST");
+    assert_eq!(requests[2].body, r#"{"name":"Indices"}"#);
+}
@@ -0,0 +1,342 @@
+mod mock_server;
+
+use capitaldotcom_api::{
+    CapitalDotComAPIBuilder, CapitalDotComInterface, InstrumentType, Resolution, SessionType,
+};
+use chrono::DateTime;
+use mock_server::{
+    login_response, MockResponse, MockServer, ALL_ACCOUNTS_JSON, SINGLE_MARKET_JSON,
+};
+
+fn market_json(epic: &str, market_status: &str) -> String {
+    format!(
+        r#"{{
+            "delayTime": 0,
+            "epic": "{epic}",
+            "symbol": "{epic}",
+            "lotSize": 1,
+            "expiry": "-",
+            "instrumentType": "SHARES",
+            "instrumentName": "{epic}",
+            "percentageChange": 0.5,
+            "updateTime": "2024-06-11T15:00:00.000",
+            "updateTimeUTC": "2024-06-11T13:00:00.000",
+            "bid": 170.0,
+            "offer": 170.2,
+            "streamingPricesAvailable": true,
+            "marketStatus": "{market_status}",
+            "scalingFactor": 1,
+            "marketModes": ["REGULAR"],
+            "pipPosition": 2,
+            "tickSize": 0.01
+        }}"#
+    )
+}
+
+#[test]
+fn list_tradeable_markets_walks_navigation() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(
+            200,
+            r#"{"nodes": [{"id": "hierarchy_v1.shares", "name": "Shares"}]}"#,
+        ),
+        MockResponse::json(
+            200,
+            &format!(
+                r#"{{
+                    "nodes": [{{"id": "hierarchy_v1.shares.us", "name": "US"}}],
+                    "markets": [{}, {}]
+                }}"#,
+                market_json("TSLA", "TRADEABLE"),
+                market_json("AAPL", "CLOSED")
+            ),
+        ),
+        MockResponse::json(
+            200,
+            &format!(
+                r#"{{"nodes": [], "markets": [{}, {}]}}"#,
+                market_json("TSLA", "TRADEABLE"),
+                market_json("NVDA", "TRADEABLE")
+            ),
+        ),
+    ]);
+    let mut capital_api = CapitalDotComAPIBuilder::new(
+        SessionType::Demo,
+        String::from("api_key"),
+        String::from("user@example.com"),
+        String::from("password"),
+    )
+    .base_url(&mock_server.url)
+    .build();
+    capital_api.open_session().unwrap();
+
+    let markets = capital_api.list_tradeable_markets().unwrap();
+    let epics: Vec<&str> = markets.iter().map(|market| market.epic.as_str()).collect();
+    assert_eq!(epics, vec!["TSLA", "NVDA"]);
+
+    // Cached, no further requests
+    assert_eq!(capital_api.list_tradeable_markets().unwrap().len(), 2);
+
+    let requests = mock_server.requests();
+    assert_eq!(requests.len(), 4);
+    assert_eq!(requests[1].path, "/api/v1/marketnavigation");
+    assert_eq!(
+        requests[2].path,
+        "/api/v1/marketnavigation/hierarchy_v1.shares?limit=500"
+    );
+}
+
+#[test]
+fn search_markets_by_type() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(
+            200,
+            r#"{"nodes": [{"id": "hierarchy_v1.all", "name": "All"}]}"#,
+        ),
+        MockResponse::json(
+            200,
+            &format!(
+                r#"{{"nodes": [], "markets": [{}, {}, {}]}}"#,
+                market_json("TSLA", "TRADEABLE"),
+                market_json("GOLD", "TRADEABLE").replace("SHARES", "COMMODITIES"),
+                market_json("OIL_CRUDE", "CLOSED").replace("SHARES", "COMMODITIES")
+            ),
+        ),
+    ]);
+    let mut capital_api = CapitalDotComAPIBuilder::new(
+        SessionType::Demo,
+        String::from("api_key"),
+        String::from("user@example.com"),
+        String::from("password"),
+    )
+    .base_url(&mock_server.url)
+    .build();
+    capital_api.open_session().unwrap();
+
+    let commodities = capital_api
+        .search_markets_by_type(InstrumentType::COMMODITIES)
+        .unwrap();
+    assert_eq!(commodities.len(), 1);
+    assert_eq!(commodities[0].epic, "GOLD");
+
+    // Filtered from the cached markets
+    let shares = capital_api
+        .search_markets_by_type(InstrumentType::SHARES)
+        .unwrap();
+    assert_eq!(shares[0].epic, "TSLA");
+    assert!(capital_api
+        .search_markets_by_type(InstrumentType::INDICES)
+        .unwrap()
+        .is_empty());
+    assert_eq!(mock_server.requests().len(), 3);
+}
+
+fn prices_json(hours: &[u32]) -> String {
+    let prices: Vec<String> = hours
+        .iter()
+        .map(|hour| {
+            format!(
+                r#"{{
+                    "snapshotTime": "2024-06-11T{hour:02}:00:00",
+                    "snapshotTimeUTC": "2024-06-11T{hour:02}:00:00",
+                    "openPrice": {{"bid": 1.0, "ask": 1.1}},
+                    "closePrice": {{"bid": 1.0, "ask": 1.1}},
+                    "highPrice": {{"bid": 1.0, "ask": 1.1}},
+                    "lowPrice": {{"bid": 1.0, "ask": 1.1}},
+                    "lastTradedVolume": 10
+                }}"#
+            )
+        })
+        .collect();
+
+    format!(
+        r#"{{"prices": [{}], "instrumentType": "SHARES"}}"#,
+        prices.join(",")
+    )
+}
+
+#[test]
+fn recent_prices_widen_window() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        // 2024-06-11T13:00:00Z
+        MockResponse::json(200, r#"{"serverTime": 1718110800000}"#),
+        MockResponse::json(200, &prices_json(&[12])),
+        MockResponse::json(200, &prices_json(&[9, 10, 12])),
+        MockResponse::json(200, r#"{"serverTime": 1718110800000}"#),
+        MockResponse::json(404, r#"{"errorCode": "error.prices.not-found"}"#),
+        MockResponse::json(200, &prices_json(&[12])),
+    ]);
+    let mut capital_api = CapitalDotComAPIBuilder::new(
+        SessionType::Demo,
+        String::from("api_key"),
+        String::from("user@example.com"),
+        String::from("password"),
+    )
+    .base_url(&mock_server.url)
+    .build();
+    capital_api.open_session().unwrap();
+
+    let prices = capital_api
+        .get_recent_prices("TSLA", Resolution::HOUR, 2)
+        .unwrap();
+    let times: Vec<&str> = prices
+        .prices
+        .iter()
+        .map(|prices| prices.snapshot_time_UTC.as_str())
+        .collect();
+    assert_eq!(times, vec!["2024-06-11T10:00:00", "2024-06-11T12:00:00"]);
+
+    let requests = mock_server.requests();
+    assert_eq!(requests[1].path, "/api/v1/time");
+    assert_eq!(
+        requests[2].path,
+        "/api/v1/prices/TSLA?resolution=HOUR&from=2024-06-11T10%3A00%3A00&to=2024-06-11T13%3A00%3A00&max=1000"
+    );
+    // 4 times the first window
+    assert!(requests[3].path.contains("from=2024-06-11T01%3A00%3A00"));
+
+    // An empty window (404) is widened like a window with too few prices
+    let prices = capital_api
+        .get_recent_prices("TSLA", Resolution::HOUR, 1)
+        .unwrap();
+    assert_eq!(prices.prices.len(), 1);
+
+    let requests = mock_server.requests();
+    assert_eq!(requests.len(), 7);
+    assert!(requests[5].path.contains("from=2024-06-11T11%3A00%3A00"));
+    assert!(requests[6].path.contains("from=2024-06-11T05%3A00%3A00"));
+}
+
+#[test]
+fn paginated_prices_skip_closed_sessions() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, SINGLE_MARKET_JSON),
+        MockResponse::json(200, &prices_json(&[13, 14])),
+        MockResponse::json(404, r#"{"errorCode": "error.prices.not-found"}"#),
+        MockResponse::json(200, &prices_json(&[0])),
+    ]);
+    let mut capital_api = CapitalDotComAPIBuilder::new(
+        SessionType::Demo,
+        String::from("api_key"),
+        String::from("user@example.com"),
+        String::from("password"),
+    )
+    .base_url(&mock_server.url)
+    .build();
+    capital_api.open_session().unwrap();
+
+    // Friday to Tuesday, open 13:30 - 20:00 UTC from Monday to Friday
+    let from = DateTime::parse_from_rfc3339("2024-06-14T00:00:00Z")
+        .unwrap()
+        .to_utc();
+    let to = DateTime::parse_from_rfc3339("2024-06-18T00:00:00Z")
+        .unwrap()
+        .to_utc();
+    let prices = capital_api
+        .get_historical_prices_paginated("TSLA", Resolution::HOUR, from, to)
+        .unwrap();
+    assert_eq!(prices.len(), 2);
+
+    // Daily candles are requested without the opening hours
+    let prices = capital_api
+        .get_historical_prices_paginated("TSLA", Resolution::DAY, from, to)
+        .unwrap();
+    assert_eq!(prices.len(), 1);
+
+    let requests = mock_server.requests();
+    assert_eq!(requests.len(), 5);
+    assert_eq!(requests[1].path, "/api/v1/markets/TSLA");
+    assert!(requests[2]
+        .path
+        .contains("from=2024-06-14T13%3A00%3A00&to=2024-06-14T20%3A00%3A00"));
+    assert!(requests[3]
+        .path
+        .contains("from=2024-06-17T13%3A00%3A00&to=2024-06-17T20%3A00%3A00"));
+    assert!(requests[4]
+        .path
+        .contains("resolution=DAY&from=2024-06-14T00%3A00%3A00&to=2024-06-18T00%3A00%3A00"));
+}
+
+#[test]
+fn candles_are_sorted_mid_prices() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, &prices_json(&[14, 13])),
+    ]);
+    let mut capital_api = CapitalDotComAPIBuilder::new(
+        SessionType::Demo,
+        String::from("api_key"),
+        String::from("user@example.com"),
+        String::from("password"),
+    )
+    .base_url(&mock_server.url)
+    .build();
+    capital_api.open_session().unwrap();
+
+    let from = DateTime::parse_from_rfc3339("2024-06-11T00:00:00Z")
+        .unwrap()
+        .to_utc();
+    let to = DateTime::parse_from_rfc3339("2024-06-12T00:00:00Z")
+        .unwrap()
+        .to_utc();
+    let candles = capital_api
+        .get_candles("TSLA", Resolution::DAY, from, to)
+        .unwrap();
+    assert_eq!(candles.len(), 2);
+    assert_eq!(candles[0].time.to_rfc3339(), "2024-06-11T13:00:00+00:00");
+    assert_eq!(candles[1].time.to_rfc3339(), "2024-06-11T14:00:00+00:00");
+    assert!((candles[0].close - 1.05).abs() < 1e-9);
+    assert_eq!(candles[0].volume, 10.);
+}
+
+#[test]
+fn current_margin_requirement() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, SINGLE_MARKET_JSON),
+        MockResponse::json(200, ALL_ACCOUNTS_JSON),
+    ]);
+    let mut capital_api = CapitalDotComAPIBuilder::new(
+        SessionType::Demo,
+        String::from("api_key"),
+        String::from("user@example.com"),
+        String::from("password"),
+    )
+    .base_url(&mock_server.url)
+    .build();
+    capital_api.open_session().unwrap();
+
+    // 20% of the offer of 175.5 with 1000 available and a size increment of 0.5
+    let margin_info = capital_api.current_margin_requirement("TSLA").unwrap();
+    assert!((margin_info.margin_per_unit - 35.1).abs() < 1e-9);
+    assert_eq!(margin_info.max_size, 28.);
+
+    let requests = mock_server.requests();
+    assert_eq!(requests[1].path, "/api/v1/markets/TSLA");
+    assert_eq!(requests[2].path, "/api/v1/accounts");
+}
+
+#[test]
+fn server_time() {
+    let mock_server = MockServer::start(vec![MockResponse::json(
+        200,
+        r#"{"serverTime": 1718110800123}"#,
+    )]);
+    let capital_api = CapitalDotComAPIBuilder::new(
+        SessionType::Demo,
+        String::from("api_key"),
+        String::from("user@example.com"),
+        String::from("password"),
+    )
+    .base_url(&mock_server.url)
+    .build();
+
+    assert_eq!(
+        capital_api.server_time().unwrap().to_rfc3339(),
+        "2024-06-11T13:00:00.123+00:00"
+    );
+}
@@ -0,0 +1,307 @@
+// Shared by several test crates, not every crate uses everything.
+#![allow(dead_code)]
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+/// Request as received by the mock server.
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Response the mock server sends for the next request.
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+impl MockResponse {
+    pub fn json(status: u16, body: &str) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.to_string(),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+
+        self
+    }
+}
+
+/// Minimal HTTP server answering the requests in order with the given responses.
+pub struct MockServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+impl MockServer {
+    pub fn start(responses: Vec<MockResponse>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded_requests = requests.clone();
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = match listener.accept() {
+                    Ok(connection) => connection,
+                    Err(_) => return,
+                };
+
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let request = Self::read_request(&mut reader);
+                recorded_requests.lock().unwrap().push(request);
+
+                let mut raw_response = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n",
+                    response.status,
+                    response.body.len()
+                );
+                for (name, value) in response.headers {
+                    raw_response.push_str(&format!("{}: {}\r\n", name, value));
+                }
+                raw_response.push_str("\r\n");
+                raw_response.push_str(&response.body);
+
+                let _ = stream.write_all(raw_response.as_bytes());
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    fn read_request(reader: &mut impl BufRead) -> RecordedRequest {
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        let mut request_line = request_line.split_whitespace();
+        let method = request_line.next().unwrap_or_default().to_string();
+        let path = request_line.next().unwrap_or_default().to_string();
+
+        let mut headers = Vec::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+
+            if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_string(), value.trim().to_string()));
+            }
+        }
+
+        let content_length = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+            .and_then(|(_, value)| value.parse().ok())
+            .unwrap_or(0);
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+
+        RecordedRequest {
+            method,
+            path,
+            headers,
+            body: String::from_utf8_lossy(&body).to_string(),
+        }
+    }
+}
+
+/// Successful login with both session tokens
+pub fn login_response() -> MockResponse {
+    MockResponse::json(200, CREATE_SESSION_JSON)
+        .header("CST", "cst_token")
+        .header("X-SECURITY-TOKEN", "security_token")
+}
+
+pub const CREATE_SESSION_JSON: &str = r#"{
+    "accountType": "CFD",
+    "accountInfo": {"balance": 1000.0, "deposit": 1000.0, "profitLoss": 0.0, "available": 1000.0},
+    "currencyIsoCode": "USD",
+    "currencySymbol": "$",
+    "currentAccountId": "0001",
+    "streamingHost": "wss://api-streaming-capital.backend-capital.com/",
+    "accounts": [
+        {
+            "accountId": "0001",
+            "accountName": "USD",
+            "preferred": true,
+            "accountType": "CFD",
+            "currency": "USD",
+            "symbol": "$",
+            "balance": {"balance": 1000.0, "deposit": 1000.0, "profitLoss": 0.0, "available": 1000.0}
+        }
+    ],
+    "clientId": "12345678",
+    "timezoneOffset": 2,
+    "hasActiveDemoAccounts": true,
+    "hasActiveLiveAccounts": false,
+    "trailingStopsEnabled": false
+}"#;
+
+pub const ALL_ACCOUNTS_JSON: &str = r#"{
+    "accounts": [
+        {
+            "accountId": "0001",
+            "accountName": "USD",
+            "status": "ENABLED",
+            "accountType": "CFD",
+            "preferred": true,
+            "balance": {"balance": 1000.0, "deposit": 1000.0, "profitLoss": 0.0, "available": 1000.0},
+            "currency": "USD",
+            "symbol": "$"
+        }
+    ]
+}"#;
+
+pub const SINGLE_MARKET_JSON: &str = r#"{
+    "instrument": {
+        "epic": "TSLA",
+        "symbol": "TSLA",
+        "expiry": "-",
+        "name": "Tesla Inc",
+        "lotSize": 1,
+        "type": "SHARES",
+        "guaranteedStopAllowed": true,
+        "streamingPricesAvailable": true,
+        "currency": "USD",
+        "marginFactor": 20,
+        "marginFactorUnit": "PERCENTAGE",
+        "openingHours": {
+            "mon": ["13:30 - 20:00"],
+            "tue": ["13:30 - 20:00"],
+            "wed": ["13:30 - 20:00"],
+            "thu": ["13:30 - 20:00"],
+            "fri": ["13:30 - 20:00"],
+            "sat": [],
+            "sun": [],
+            "zone": "UTC"
+        },
+        "overnightFee": {
+            "longRate": -0.0252,
+            "shortRate": 0.0029,
+            "swapChargeTimestamp": 1718139600000,
+            "swapChargeInterval": 1440
+        }
+    },
+    "dealingRules": {
+        "minStepDistance": {"unit": "POINTS", "value": 0.01},
+        "minDealSize": {"unit": "POINTS", "value": 0.5},
+        "maxDealSize": {"unit": "POINTS", "value": 1000.0},
+        "minSizeIncrement": {"unit": "POINTS", "value": 0.5},
+        "minGuaranteedStopDistance": {"unit": "PERCENTAGE", "value": 5.0},
+        "minStopOrProfitDistance": {"unit": "POINTS", "value": 1.0},
+        "maxStopOrProfitDistance": {"unit": "POINTS", "value": 100.0},
+        "marketOrderPreference": "AVAILABLE_DEFAULT_OFF",
+        "trailingStopsPreference": "AVAILABLE"
+    },
+    "snapshot": {
+        "marketStatus": "TRADEABLE",
+        "netChange": 1.52,
+        "percentageChange": 0.87,
+        "updateTime": "2024-06-11T15:00:00.000",
+        "delayTime": 0,
+        "bid": 175.0,
+        "offer": 175.5,
+        "high": 178.2,
+        "low": 172.9,
+        "decimalPlacesFactor": 2,
+        "scalingFactor": 1,
+        "marketModes": ["REGULAR"]
+    }
+}"#;
+
+pub fn order_confirmation_json(status: &str, deal_status: &str) -> String {
+    format!(
+        r#"{{
+            "date": "2024-06-11T13:00:00.000",
+            "status": "{status}",
+            "dealStatus": "{deal_status}",
+            "epic": "TSLA",
+            "dealReference": "o_0001",
+            "dealId": "0001",
+            "affectedDeals": [{{"dealId": "0001", "status": "OPENED"}}],
+            "level": 170.0,
+            "size": 1,
+            "direction": "BUY",
+            "guaranteedStop": false,
+            "trailingStop": false
+        }}"#
+    )
+}
+
+pub fn position_json(deal_id: &str, direction: &str, size: f32) -> String {
+    format!(
+        r#"{{
+            "position": {{
+                "contractSize": 1,
+                "createdDate": "2024-06-11T15:00:00.000",
+                "createdDateUTC": "2024-06-11T13:00:00.000",
+                "dealId": "{deal_id}",
+                "dealReference": "p_{deal_id}",
+                "size": {size},
+                "leverage": 5,
+                "upl": 0.0,
+                "direction": "{direction}",
+                "level": 170.0,
+                "currency": "USD",
+                "guaranteedStop": false
+            }},
+            "market": {{
+                "instrumentName": "Tesla Inc",
+                "expiry": "-",
+                "marketStatus": "TRADEABLE",
+                "epic": "TSLA",
+                "symbol": "TSLA",
+                "instrumentType": "SHARES",
+                "lotSize": 1,
+                "high": 180.0,
+                "low": 170.0,
+                "percentageChange": 1.0,
+                "netChange": 1.5,
+                "bid": 172.0,
+                "offer": 172.5,
+                "updateTime": "2024-06-11T15:00:00.000",
+                "updateTimeUTC": "2024-06-11T13:00:00.000",
+                "delayTime": 0,
+                "streamingPricesAvailable": true,
+                "scalingFactor": 1,
+                "marketModes": ["REGULAR"]
+            }}
+        }}"#
+    )
+}
+
+pub const ACCOUNT_PREFERENCES_JSON: &str = r#"{
+    "hedgingMode": false,
+    "leverages": {
+        "SHARES": {"current": 5, "available": [1, 2, 3, 4, 5]},
+        "CURRENCIES": {"current": 30, "available": [1, 2, 5, 10, 20, 30]},
+        "INDICES": {"current": 20, "available": [1, 2, 5, 10, 20]},
+        "CRYPTOCURRENCIES": {"current": 2, "available": [1, 2]},
+        "COMMODITIES": {"current": 10, "available": [1, 2, 5, 10]}
+    }
+}"#;
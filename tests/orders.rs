@@ -0,0 +1,732 @@
+mod mock_server;
+
+use capitaldotcom_api::{
+    request_bodies::{ClosePositionBody, CreatePositionBodyBuilder, PositionUpdateBodyBuilder},
+    responses::{PositionResponse, SingleMarketDetailsResponse},
+    CapitalDotComAPI, CapitalDotComAPIBuilder, CapitalDotComError, CapitalDotComInterface,
+    Direction, OrderType, SessionType,
+};
+use mock_server::{
+    login_response, order_confirmation_json, position_json, MockResponse, MockServer,
+    ACCOUNT_PREFERENCES_JSON, ALL_ACCOUNTS_JSON, SINGLE_MARKET_JSON,
+};
+
+fn logged_in_client(mock_server: &MockServer) -> CapitalDotComAPI {
+    let mut capital_api = CapitalDotComAPIBuilder::new(
+        SessionType::Demo,
+        String::from("api_key"),
+        String::from("user@example.com"),
+        String::from("password"),
+    )
+    .base_url(&mock_server.url)
+    .build();
+
+    capital_api.open_session().unwrap();
+
+    capital_api
+}
+
+#[test]
+fn open_position_waits_for_confirmation() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, r#"{"dealReference": "o_0001"}"#),
+        MockResponse::json(404, r#"{"errorCode": "error.not-found.dealReference"}"#),
+        MockResponse::json(200, &order_confirmation_json("PENDING", "ACCEPTED")),
+        MockResponse::json(200, &order_confirmation_json("OPEN", "ACCEPTED")),
+    ]);
+    let capital_api = logged_in_client(&mock_server);
+
+    let position_data = CreatePositionBodyBuilder::new(Direction::BUY, "TSLA", 1.)
+        .build()
+        .unwrap();
+    let confirmation = capital_api.open_position(position_data).unwrap();
+    assert!(!confirmation.is_pending());
+    assert_eq!(confirmation.deal_id, "0001");
+
+    let requests = mock_server.requests();
+    assert_eq!(requests.len(), 5);
+    assert_eq!(requests[1].path, "/api/v1/positions");
+    assert_eq!(requests[4].path, "/api/v1/confirms/o_0001");
+}
+
+#[test]
+fn open_position_detailed_returns_deal_reference() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, r#"{"dealReference": "o_0001"}"#),
+        MockResponse::json(200, &order_confirmation_json("OPEN", "ACCEPTED")),
+    ]);
+    let capital_api = logged_in_client(&mock_server);
+
+    let position_data = CreatePositionBodyBuilder::new(Direction::BUY, "TSLA", 1.)
+        .build()
+        .unwrap();
+    let (deal_reference, confirmation) = capital_api.open_position_detailed(position_data).unwrap();
+    assert_eq!(deal_reference.deal_reference, "o_0001");
+    assert_eq!(confirmation.deal_id, "0001");
+}
+
+#[test]
+fn submit_and_confirm_position() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, r#"{"dealReference": "o_0001"}"#),
+        MockResponse::json(200, &order_confirmation_json("OPEN", "ACCEPTED")),
+    ]);
+    let capital_api = logged_in_client(&mock_server);
+
+    let position_data = CreatePositionBodyBuilder::new(Direction::BUY, "TSLA", 1.)
+        .build()
+        .unwrap();
+    let pending_order = capital_api.submit_position(position_data).unwrap();
+    assert_eq!(pending_order.deal_reference, "o_0001");
+    assert_eq!(mock_server.requests().len(), 2);
+
+    let confirmed_order = pending_order.confirm(&capital_api).unwrap();
+    assert_eq!(confirmed_order.deal_reference, "o_0001");
+    assert_eq!(confirmed_order.deal_id, "0001");
+    assert_eq!(mock_server.requests()[2].path, "/api/v1/confirms/o_0001");
+}
+
+#[test]
+fn failed_confirmation_keeps_deal_reference() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, r#"{"dealReference": "o_0001"}"#),
+        MockResponse::json(400, r#"{"errorCode": "error.invalid.details"}"#),
+    ]);
+    let capital_api = logged_in_client(&mock_server);
+
+    let position_data = CreatePositionBodyBuilder::new(Direction::BUY, "TSLA", 1.)
+        .build()
+        .unwrap();
+    match capital_api.open_position(position_data) {
+        Err(CapitalDotComError::ConfirmationFailed { deal_reference, .. }) => {
+            assert_eq!(deal_reference, "o_0001")
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn rejected_confirmation() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, r#"{"dealReference": "o_0002"}"#),
+        MockResponse::json(
+            200,
+            r#"{
+                "date": "2024-06-11T13:05:21.384",
+                "dealStatus": "REJECTED",
+                "epic": "TSLA",
+                "rejectReason": "RISK_CHECK",
+                "dealReference": "o_0002",
+                "dealId": "0002",
+                "affectedDeals": [],
+                "level": 0,
+                "size": 1000,
+                "direction": "BUY",
+                "guaranteedStop": false,
+                "trailingStop": false
+            }"#,
+        ),
+    ]);
+    let capital_api = logged_in_client(&mock_server);
+
+    let position_data = CreatePositionBodyBuilder::new(Direction::BUY, "TSLA", 1000.)
+        .build()
+        .unwrap();
+    match capital_api.open_position(position_data) {
+        Err(CapitalDotComError::OrderRejected {
+            deal_reference,
+            reason,
+        }) => {
+            assert_eq!(deal_reference, "o_0002");
+            assert_eq!(reason.as_deref(), Some("RISK_CHECK"));
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+}
+
+fn working_order_json(deal_id: &str) -> String {
+    format!(
+        r#"{{
+            "workingOrderData": {{
+                "dealId": "{deal_id}",
+                "direction": "BUY",
+                "epic": "SILVER",
+                "orderSize": 1,
+                "leverage": 20,
+                "orderLevel": 20.5,
+                "timeInForce": "GOOD_TILL_CANCELLED",
+                "createdDate": "2024-06-11T15:00:00.000",
+                "createdDateUTC": "2024-06-11T13:00:00.000",
+                "guaranteedStop": false,
+                "orderType": "LIMIT",
+                "trailingStop": false,
+                "currencyCode": "USD"
+            }},
+            "marketData": {{
+                "instrumentName": "Silver",
+                "expiry": "-",
+                "marketStatus": "TRADEABLE",
+                "epic": "SILVER",
+                "symbol": "Silver",
+                "instrumentType": "COMMODITIES",
+                "lotSize": 1,
+                "high": 30.1,
+                "low": 29.4,
+                "percentageChange": 0.5,
+                "netChange": 0.15,
+                "bid": 29.8,
+                "offer": 29.83,
+                "updateTime": "2024-06-11T15:00:00.000",
+                "updateTimeUTC": "2024-06-11T13:00:00.000",
+                "delayTime": 0,
+                "streamingPricesAvailable": true,
+                "scalingFactor": 1,
+                "marketModes": ["REGULAR"]
+            }}
+        }}"#
+    )
+}
+
+#[test]
+fn cancel_all_working_orders_reports_each_order() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(
+            200,
+            &format!(
+                r#"{{"workingOrders": [{}, {}]}}"#,
+                working_order_json("0003"),
+                working_order_json("0004")
+            ),
+        ),
+        MockResponse::json(200, r#"{"dealReference": "o_0003"}"#),
+        MockResponse::json(404, r#"{"errorCode": "error.not-found.dealId"}"#),
+    ]);
+    let capital_api = logged_in_client(&mock_server);
+
+    let results = capital_api.cancel_all_working_orders().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].0, "0003");
+    assert_eq!(results[0].1.as_ref().unwrap().deal_reference, "o_0003");
+    assert_eq!(results[1].0, "0004");
+    assert_eq!(results[1].1.as_ref().unwrap_err().status(), Some(404));
+
+    let requests = mock_server.requests();
+    assert_eq!(requests[2].method, "DELETE");
+    assert_eq!(requests[2].path, "/api/v1/workingorders/0003");
+    assert_eq!(requests[3].path, "/api/v1/workingorders/0004");
+}
+
+#[test]
+fn reconcile_fetches_positions_orders_and_activity() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(
+            200,
+            &format!(
+                r#"{{"positions": [{}]}}"#,
+                position_json("0002", "BUY", 10.)
+            ),
+        ),
+        MockResponse::json(
+            200,
+            &format!(r#"{{"workingOrders": [{}]}}"#, working_order_json("0003")),
+        ),
+        MockResponse::json(200, include_str!("fixtures/activity_history.json")),
+    ]);
+    let capital_api = logged_in_client(&mock_server);
+
+    let snapshot = capital_api.reconcile().unwrap();
+    assert_eq!(snapshot.positions[0].position.deal_id, "0002");
+    assert_eq!(
+        snapshot.working_orders[0].working_order_data.deal_id,
+        "0003"
+    );
+    assert!(snapshot.recent_activity[0].is_position_opened());
+
+    let requests = mock_server.requests();
+    assert_eq!(requests[1].path, "/api/v1/positions");
+    assert_eq!(requests[2].path, "/api/v1/workingorders");
+    assert!(requests[3]
+        .path
+        .starts_with("/api/v1/history/activity?from="));
+}
+
+/// SINGLE_MARKET_JSON with stop/profit limits of 1% to 50% of the price
+fn percentage_limits_market_json() -> String {
+    SINGLE_MARKET_JSON
+        .replace(
+            r#""minStopOrProfitDistance": {"unit": "POINTS", "value": 1.0}"#,
+            r#""minStopOrProfitDistance": {"unit": "PERCENTAGE", "value": 1.0}"#,
+        )
+        .replace(
+            r#""maxStopOrProfitDistance": {"unit": "POINTS", "value": 100.0}"#,
+            r#""maxStopOrProfitDistance": {"unit": "PERCENTAGE", "value": 50.0}"#,
+        )
+}
+
+#[test]
+fn bracket_order_checks_percentage_limits() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, &percentage_limits_market_json()),
+        MockResponse::json(200, &percentage_limits_market_json()),
+        MockResponse::json(200, r#"{"dealReference": "o_0001"}"#),
+        MockResponse::json(200, &order_confirmation_json("OPEN", "ACCEPTED")),
+    ]);
+    let capital_api = logged_in_client(&mock_server);
+
+    // 1% of the offer of 175.5 is 1.755
+    assert!(matches!(
+        capital_api.place_bracket_order(Direction::BUY, "TSLA", 1., 1.5, 10.),
+        Err(CapitalDotComError::InvalidDistance(distance)) if distance == 1.5
+    ));
+
+    let confirmation = capital_api
+        .place_bracket_order(Direction::BUY, "TSLA", 1., 2., 80.)
+        .unwrap();
+    assert_eq!(confirmation.deal_reference, "o_0001");
+
+    let requests = mock_server.requests();
+    assert_eq!(requests.len(), 5);
+    let body: serde_json::Value = serde_json::from_str(&requests[3].body).unwrap();
+    assert_eq!(body["stopDistance"], 2.);
+    assert_eq!(body["profitDistance"], 80.);
+}
+
+#[test]
+fn open_position_retries_after_bad_gateway() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(502, r#"{"errorCode": "error.bad-gateway"}"#),
+        MockResponse::json(
+            200,
+            &format!(r#"{{"positions": [{}]}}"#, position_json("0002", "BUY", 1.)),
+        ),
+        MockResponse::json(200, r#"{"dealReference": "o_0001"}"#),
+        MockResponse::json(200, &order_confirmation_json("OPEN", "ACCEPTED")),
+    ]);
+    let capital_api = logged_in_client(&mock_server);
+
+    let position_data = CreatePositionBodyBuilder::new(Direction::BUY, "TSLA", 1.)
+        .build()
+        .unwrap();
+    let confirmation = capital_api
+        .open_position_with_retries(position_data, 1)
+        .unwrap();
+    assert_eq!(confirmation.deal_id, "0001");
+
+    // The older position with the same epic, direction and size is not taken for the lost one
+    let requests = mock_server.requests();
+    assert_eq!(requests.len(), 5);
+    assert_eq!(requests[2].path, "/api/v1/positions");
+    assert_eq!(requests[3].path, "/api/v1/positions");
+}
+
+#[test]
+fn risk_based_size_uses_value_per_point() {
+    // A point is worth 1 / 100 for a size of 1, while the lot_size is 1
+    let market_json =
+        SINGLE_MARKET_JSON.replace(r#""scalingFactor": 1"#, r#""scalingFactor": 100"#);
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, ALL_ACCOUNTS_JSON),
+        MockResponse::json(200, &market_json),
+        MockResponse::json(200, r#"{"dealReference": "o_0001"}"#),
+        MockResponse::json(200, &order_confirmation_json("OPEN", "ACCEPTED")),
+    ]);
+    let capital_api = logged_in_client(&mock_server);
+
+    // 1% of the available 1000 at a stop distance of 10
+    let (size, _) = capital_api
+        .open_position_risk_based(Direction::BUY, "TSLA", 0.01, 10.)
+        .unwrap();
+    assert_eq!(size, 100.);
+
+    // The position of that size loses the risked 10 at the stop
+    let mut position: PositionResponse = serde_json::from_str(
+        &position_json("0001", "BUY", size as f32)
+            .replace(r#""scalingFactor": 1"#, r#""scalingFactor": 100"#),
+    )
+    .unwrap();
+    position.market.bid = position.position.level - 10.;
+    assert!((position.computed_pl() + 10.).abs() < 1e-9);
+
+    let market: SingleMarketDetailsResponse = serde_json::from_str(&market_json).unwrap();
+    assert_eq!(market.value_per_point(), position.value_per_point());
+}
+
+#[test]
+fn guaranteed_stop_checks_percentage_limit() {
+    // 5% of the offer of 175.5
+    let market: SingleMarketDetailsResponse = serde_json::from_str(SINGLE_MARKET_JSON).unwrap();
+
+    let position_data = CreatePositionBodyBuilder::new(Direction::BUY, "TSLA", 1.)
+        .stop_distance(5.)
+        .guaranteed_stop(true)
+        .build()
+        .unwrap();
+    match position_data.check_guaranteed_stop(&market) {
+        Err(CapitalDotComError::InvalidGuaranteedStopDistance {
+            distance,
+            min_distance,
+        }) => {
+            assert_eq!(distance, 5.);
+            assert!((min_distance - 8.775).abs() < 1e-9);
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+
+    let position_data = CreatePositionBodyBuilder::new(Direction::BUY, "TSLA", 1.)
+        .stop_distance(9.)
+        .guaranteed_stop(true)
+        .build()
+        .unwrap();
+    assert!(position_data.check_guaranteed_stop(&market).is_ok());
+}
+
+#[test]
+fn min_order_body_uses_min_deal_size() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, SINGLE_MARKET_JSON),
+    ]);
+    let capital_api = logged_in_client(&mock_server);
+
+    let body = capital_api.min_order_body("TSLA", Direction::SELL).unwrap();
+    let body = serde_json::to_value(body).unwrap();
+    assert_eq!(body["epic"], "TSLA");
+    assert_eq!(body["direction"], "SELL");
+    assert_eq!(body["size"], 0.5);
+
+    assert_eq!(mock_server.requests()[1].path, "/api/v1/markets/TSLA");
+}
+
+#[test]
+fn trading_disabled_blocks_orders() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, r#"{"positions": []}"#),
+    ]);
+    let capital_api = logged_in_client(&mock_server);
+
+    capital_api.set_trading_enabled(false);
+    assert!(!capital_api.is_trading_enabled());
+
+    let position_data = CreatePositionBodyBuilder::new(Direction::BUY, "TSLA", 1.)
+        .build()
+        .unwrap();
+    assert!(matches!(
+        capital_api.open_position(position_data),
+        Err(CapitalDotComError::TradingDisabled)
+    ));
+    assert!(matches!(
+        capital_api.close_position("0001"),
+        Err(CapitalDotComError::TradingDisabled)
+    ));
+    assert!(matches!(
+        capital_api.delete_working_order("0003"),
+        Err(CapitalDotComError::TradingDisabled)
+    ));
+
+    // Reads keep working
+    assert!(capital_api.get_all_positions().is_ok());
+    assert_eq!(mock_server.requests().len(), 2);
+}
+
+#[test]
+fn close_position_with_limit_order() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, &position_json("0001", "BUY", 10.)),
+        MockResponse::json(200, ACCOUNT_PREFERENCES_JSON),
+        MockResponse::json(200, r#"{"dealReference": "o_0002"}"#),
+        MockResponse::json(
+            200,
+            &order_confirmation_json("OPEN", "ACCEPTED").replace("o_0001", "o_0002"),
+        ),
+        MockResponse::json(200, &position_json("0001", "BUY", 10.)),
+        MockResponse::json(200, ACCOUNT_PREFERENCES_JSON),
+        MockResponse::json(200, r#"{"dealReference": "o_0003"}"#),
+        MockResponse::json(
+            200,
+            &order_confirmation_json("CLOSED", "ACCEPTED").replace("o_0001", "o_0003"),
+        ),
+        MockResponse::json(200, &position_json("0001", "BUY", 10.)),
+        MockResponse::json(
+            200,
+            &ACCOUNT_PREFERENCES_JSON.replace(r#""hedgingMode": false"#, r#""hedgingMode": true"#),
+        ),
+    ]);
+    let capital_api = logged_in_client(&mock_server);
+
+    let close_position_data = ClosePositionBody::new(Some(OrderType::LIMIT), Some(180.), Some(4.));
+    let confirmation = capital_api
+        .close_position_with("0001", close_position_data)
+        .unwrap();
+    assert_eq!(confirmation.deal_reference, "o_0002");
+
+    let confirmation = capital_api
+        .close_position_with("0001", ClosePositionBody::new(None, None, None))
+        .unwrap();
+    assert_eq!(confirmation.deal_reference, "o_0003");
+
+    assert!(matches!(
+        capital_api.close_position_with("0001", ClosePositionBody::new(None, None, None)),
+        Err(CapitalDotComError::HedgingModeEnabled)
+    ));
+
+    // Checked before any request
+    assert!(matches!(
+        capital_api.close_position_with("0001", ClosePositionBody::new(None, Some(180.), None)),
+        Err(CapitalDotComError::ConflictingOrderParameters(_))
+    ));
+    capital_api.set_trading_enabled(false);
+    assert!(matches!(
+        capital_api.close_position_with("0001", ClosePositionBody::new(None, None, None)),
+        Err(CapitalDotComError::TradingDisabled)
+    ));
+
+    let requests = mock_server.requests();
+    assert_eq!(requests.len(), 11);
+    assert_eq!(requests[4].path, "/api/v1/confirms/o_0002");
+    assert_eq!(requests[1].path, "/api/v1/positions/0001");
+    assert_eq!(requests[3].path, "/api/v1/workingorders");
+    let body: serde_json::Value = serde_json::from_str(&requests[3].body).unwrap();
+    assert_eq!(body["direction"], "SELL");
+    assert_eq!(body["epic"], "TSLA");
+    assert_eq!(body["size"], 4.);
+    assert_eq!(body["level"], 180.);
+    assert_eq!(body["type"], "LIMIT");
+
+    assert_eq!(requests[7].path, "/api/v1/positions");
+    let body: serde_json::Value = serde_json::from_str(&requests[7].body).unwrap();
+    assert_eq!(body["direction"], "SELL");
+    assert_eq!(body["size"], 10.);
+}
+
+#[test]
+fn position_with_fresh_market() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, &position_json("0001", "BUY", 10.)),
+        MockResponse::json(200, SINGLE_MARKET_JSON),
+    ]);
+    let capital_api = logged_in_client(&mock_server);
+
+    let (position, market) = capital_api.position_with_fresh_market("0001").unwrap();
+    assert_eq!(position.market.bid, 172.);
+    assert_eq!(market.snapshot.bid, 175.);
+    assert_eq!(
+        position.distance_to_stop(market.snapshot.bid, market.snapshot.offer),
+        None
+    );
+
+    let requests = mock_server.requests();
+    assert_eq!(requests[1].path, "/api/v1/positions/0001");
+    assert_eq!(requests[2].path, "/api/v1/markets/TSLA");
+}
+
+#[test]
+fn hedge_position_needs_hedging_mode() {
+    let hedging_preferences =
+        ACCOUNT_PREFERENCES_JSON.replace(r#""hedgingMode": false"#, r#""hedgingMode": true"#);
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, &position_json("0001", "BUY", 10.)),
+        MockResponse::json(200, ACCOUNT_PREFERENCES_JSON),
+        MockResponse::json(200, &position_json("0001", "BUY", 10.)),
+        MockResponse::json(200, &hedging_preferences),
+        MockResponse::json(200, r#"{"dealReference": "o_0001"}"#),
+        MockResponse::json(200, &order_confirmation_json("OPEN", "ACCEPTED")),
+    ]);
+    let capital_api = logged_in_client(&mock_server);
+
+    assert!(matches!(
+        capital_api.hedge_position("0001"),
+        Err(CapitalDotComError::HedgingModeDisabled)
+    ));
+
+    let confirmation = capital_api.hedge_position("0001").unwrap();
+    assert_eq!(confirmation.deal_reference, "o_0001");
+
+    let requests = mock_server.requests();
+    assert_eq!(requests.len(), 7);
+    assert_eq!(requests[5].path, "/api/v1/positions");
+    let body: serde_json::Value = serde_json::from_str(&requests[5].body).unwrap();
+    assert_eq!(body["direction"], "SELL");
+    assert_eq!(body["epic"], "TSLA");
+    assert_eq!(body["size"], 10.);
+}
+
+#[test]
+fn add_to_position_in_the_same_direction() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, &position_json("0001", "SELL", 10.)),
+        MockResponse::json(200, SINGLE_MARKET_JSON),
+        MockResponse::json(200, &position_json("0001", "SELL", 10.)),
+        MockResponse::json(200, SINGLE_MARKET_JSON),
+        MockResponse::json(200, r#"{"dealReference": "o_0001"}"#),
+        MockResponse::json(200, &order_confirmation_json("OPEN", "ACCEPTED")),
+    ]);
+    let capital_api = logged_in_client(&mock_server);
+
+    // Above max_deal_size
+    assert!(matches!(
+        capital_api.add_to_position("0001", 2000.),
+        Err(CapitalDotComError::InvalidDealSize(_))
+    ));
+
+    let confirmation = capital_api.add_to_position("0001", 5.).unwrap();
+    assert_eq!(confirmation.deal_reference, "o_0001");
+
+    let requests = mock_server.requests();
+    assert_eq!(requests.len(), 7);
+    assert_eq!(requests[5].path, "/api/v1/positions");
+    let body: serde_json::Value = serde_json::from_str(&requests[5].body).unwrap();
+    assert_eq!(body["direction"], "SELL");
+    assert_eq!(body["epic"], "TSLA");
+    assert_eq!(body["size"], 5.);
+}
+
+#[test]
+fn trailing_stop_needs_account_support() {
+    let mock_server = MockServer::start(vec![login_response()]);
+    let capital_api = logged_in_client(&mock_server);
+    assert!(!capital_api.trailing_stops_enabled());
+
+    let position = CreatePositionBodyBuilder::new(Direction::BUY, "TSLA", 1.)
+        .stop_distance(5.)
+        .trailing_stop(true)
+        .build()
+        .unwrap();
+    assert!(matches!(
+        capital_api.open_position(position),
+        Err(CapitalDotComError::TrailingStopsDisabled)
+    ));
+    assert_eq!(mock_server.requests().len(), 1);
+}
+
+#[test]
+fn update_position_skips_unchanged_stops() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, &position_json("0001", "BUY", 10.)),
+        MockResponse::json(200, r#"{"dealReference": "o_0001"}"#),
+    ]);
+    let capital_api = logged_in_client(&mock_server);
+
+    let mut position = capital_api.get_position_data("0001").unwrap();
+    position.position.stop_level = Some(165.);
+
+    let unchanged = PositionUpdateBodyBuilder::new()
+        .stop_level(165.)
+        .build()
+        .unwrap();
+    assert!(matches!(
+        capital_api.update_position(&position, unchanged),
+        Err(CapitalDotComError::NoChanges)
+    ));
+
+    let changed = PositionUpdateBodyBuilder::new()
+        .stop_level(166.)
+        .build()
+        .unwrap();
+    let body = capital_api.update_position(&position, changed).unwrap();
+    assert_eq!(body.deal_reference, "o_0001");
+
+    let requests = mock_server.requests();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(requests[2].method, "PUT");
+    assert_eq!(requests[2].path, "/api/v1/positions/0001");
+    assert_eq!(requests[2].body, r#"{"stopLevel":166.0}"#);
+}
+
+#[test]
+fn get_position_data_by_deal_id() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, include_str!("fixtures/position.json")),
+    ]);
+    let capital_api = logged_in_client(&mock_server);
+
+    let position = capital_api.get_position_data("0002").unwrap();
+    assert_eq!(position.position.deal_id, "0002");
+    assert_eq!(
+        position.position.working_order_id.as_deref(),
+        Some("o_0001")
+    );
+    assert_eq!(position.position.stop_level, Some(165.));
+    assert_eq!(position.position.profit_level, Some(185.));
+    assert_eq!(position.market.epic, "TSLA");
+
+    let requests = mock_server.requests();
+    assert_eq!(requests[1].method, "GET");
+    assert_eq!(requests[1].path, "/api/v1/positions/0002");
+}
+
+#[test]
+fn confirmed_deal_id_closes_the_position() {
+    // The deal_id of the confirmation is not the deal_id of the opened position
+    let confirmation = order_confirmation_json("OPEN", "ACCEPTED")
+        .replace(r#"{"dealId": "0001""#, r#"{"dealId": "0002""#)
+        .replace(r#""dealId": "0001""#, r#""dealId": "o_deal""#);
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, r#"{"dealReference": "o_0001"}"#),
+        MockResponse::json(200, &confirmation),
+        MockResponse::json(200, r#"{"dealReference": "o_0002"}"#),
+    ]);
+    let capital_api = logged_in_client(&mock_server);
+
+    let position_data = CreatePositionBodyBuilder::new(Direction::BUY, "TSLA", 1.)
+        .build()
+        .unwrap();
+    let confirmed_order = capital_api
+        .submit_position(position_data)
+        .unwrap()
+        .confirm(&capital_api)
+        .unwrap();
+    assert_eq!(confirmed_order.deal_id, "0002");
+    assert_eq!(confirmed_order.confirmation.deal_id, "o_deal");
+
+    capital_api
+        .close_position(&confirmed_order.deal_id)
+        .unwrap();
+
+    let requests = mock_server.requests();
+    assert_eq!(requests[3].method, "DELETE");
+    assert_eq!(requests[3].path, "/api/v1/positions/0002");
+}
+
+#[test]
+fn close_position_confirmed_checks_the_position() {
+    let mock_server = MockServer::start(vec![
+        login_response(),
+        MockResponse::json(200, r#"{"dealReference": "o_0001"}"#),
+        MockResponse::json(200, &order_confirmation_json("CLOSED", "ACCEPTED")),
+        MockResponse::json(404, r#"{"errorCode": "error.not-found.dealId"}"#),
+        MockResponse::json(500, r#"{"errorCode": "error.internal"}"#),
+        MockResponse::json(200, &position_json("0001", "BUY", 10.)),
+    ]);
+    let capital_api = logged_in_client(&mock_server);
+
+    assert!(capital_api.close_position_confirmed("0001").is_ok());
+
+    match capital_api.close_position_confirmed("0001") {
+        Err(CapitalDotComError::PositionStillOpen(deal_id)) => assert_eq!(deal_id, "0001"),
+        result => panic!("unexpected result {:?}", result),
+    }
+
+    let requests = mock_server.requests();
+    assert_eq!(requests.len(), 6);
+    assert_eq!(requests[1].method, "DELETE");
+    assert_eq!(requests[3].path, "/api/v1/positions/0001");
+    assert_eq!(requests[5].path, "/api/v1/positions/0001");
+}
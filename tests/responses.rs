@@ -0,0 +1,134 @@
+use capitaldotcom_api::responses::*;
+use capitaldotcom_api::{Currency, Direction, Expiry, InstrumentType, TransactionType};
+use serde::de::DeserializeOwned;
+
+/// Deserialize tests/fixtures/<name>.json, panics with the serde error if the shape does not match.
+fn fixture<T: DeserializeOwned>(name: &str) -> T {
+    let path = format!("{}/tests/fixtures/{name}.json", env!("CARGO_MANIFEST_DIR"));
+    let json = std::fs::read_to_string(&path).unwrap();
+
+    serde_json::from_str(&json).unwrap_or_else(|error| panic!("{path}: {error}"))
+}
+
+#[test]
+fn session_responses() {
+    let server_time: ServerTimeResponse = fixture("server_time");
+    assert!(server_time.date_time().is_some());
+
+    // The ping status "OK" is not one of the deal statuses
+    let ping: PingResponse = fixture("ping");
+    assert_eq!(ping.status, Status::Unknown);
+
+    let encryption_key: EncryptionKeyResponse = fixture("encryption_key");
+    assert_eq!(encryption_key.time_stamp, 1718110800000);
+
+    let session: CreateNewSessionResponse = fixture("create_session");
+    assert_eq!(session.current_account_id, "0001");
+    assert_eq!(session.accounts.len(), 1);
+
+    let session_details: SessionDetailsResponse = fixture("session_details");
+    assert_eq!(session_details.currency, Currency::USD);
+    assert_eq!(session_details.timezone().local_minus_utc(), 7200);
+
+    let logout: SessionLogOutResponse = fixture("logout");
+    assert_eq!(logout.status, LogoutStatus::SUCCESS);
+}
+
+#[test]
+fn account_responses() {
+    let all_accounts: AllAccountsResponse = fixture("all_accounts");
+    assert_eq!(all_accounts.currencies(), vec![Currency::USD]);
+
+    let switch_account: SwitchAccountResponse = fixture("switch_account");
+    assert!(switch_account.dealing_enabled);
+
+    let preferences: AccountPreferencesResponse = fixture("account_preferences");
+    assert_eq!(preferences.leverages["SHARES"].current, 5);
+
+    let activities: ActivityHistoryResponse = fixture("activity_history");
+    assert!(activities.activities[0].is_position_opened());
+
+    let transactions: TransactionHistoryResponse = fixture("transaction_history");
+    assert_eq!(
+        transactions.transactions[1].transaction_type,
+        TransactionType::Swap
+    );
+    assert_eq!(transactions.realized_pl_by_currency()[&Currency::USD], 15.);
+}
+
+#[test]
+fn trading_responses() {
+    let deal_reference: DealReferenceResponse = fixture("deal_reference");
+    assert_eq!(deal_reference.deal_reference, "o_0001");
+
+    let confirmation: OrderConfirmationResponse = fixture("order_confirmation");
+    assert_eq!(confirmation.deal_status, DealStatus::ACCEPTED);
+    assert_eq!(confirmation.affected_deals[0].status, Status::OPENED);
+    assert_eq!(confirmation.total_filled_size(), 10.);
+
+    let positions: AllPositionsResponse = fixture("all_positions");
+    let position = &positions.positions[0];
+    assert_eq!(position.position.direction, Direction::BUY);
+    assert_eq!(position.position.stop_level, Some(165.));
+    assert_eq!(position.market.expiry, Expiry::None);
+
+    let working_orders: AllWorkingOrdersResponse = fixture("all_working_orders");
+    let working_order = &working_orders.working_orders[0].working_order_data;
+    assert_eq!(working_order.order_level, 180.);
+    assert_eq!(working_order.profit_distance, Some(10.));
+    assert!(working_order.stop_level.is_none());
+}
+
+#[test]
+fn market_responses() {
+    let markets: MarketDetailsResponse = fixture("market_details");
+    assert_eq!(markets.markets[0].instrument_type, InstrumentType::SHARES);
+    assert_eq!(markets.markets[0].pip_position, Some(2));
+    assert_eq!(markets.markets[0].scaling_factor, 1.);
+
+    let navigation: MarketNavigationResponse = fixture("market_navigation");
+    assert_eq!(navigation.nodes.len(), 2);
+    assert!(navigation.markets.is_empty());
+
+    let market: SingleMarketDetailsResponse = fixture("single_market_details");
+    assert_eq!(market.instrument.epic, "TSLA");
+    assert!(market.snapshot.is_tradeable());
+    assert_eq!(market.snapshot.scaling_factor, 1.);
+
+    let prices: HistoricalPricesResponse = fixture("historical_prices");
+    assert_eq!(prices.instrument_type, InstrumentType::SHARES);
+    assert_eq!(prices.prices[0].close_price.bid, 173.);
+    assert!(prices.prices[0].snapshot_time_utc().is_some());
+}
+
+#[test]
+fn optional_market_fields() {
+    // Shares come without pipPosition, tickSize and marketModes
+    let markets: MarketDetailsResponse = fixture("market_details_share");
+    assert_eq!(markets.markets[0].epic, "AAPL");
+    assert!(markets.markets[0].pip_position.is_none());
+    assert!(markets.markets[0].tick_size.is_none());
+    assert!(markets.markets[0].market_modes.is_empty());
+
+    // A commodity future without overnight fee, opening hours and guaranteed stops
+    let market: SingleMarketDetailsResponse = fixture("single_market_commodity");
+    assert!(matches!(market.instrument.expiry, Expiry::Date(_)));
+    assert!(market.instrument.overnight_fee.is_none());
+    assert!(market.instrument.opening_hours.is_none());
+    assert!(market.dealing_rules.min_guaranteed_stop_distance.is_none());
+    assert!(market
+        .dealing_rules
+        .is_valid_guaranteed_stop_distance_at(0.5, market.snapshot.bid));
+    assert!(market.snapshot.is_tradeable());
+
+    let market: SingleMarketDetailsResponse = fixture("single_market_details");
+    assert!(market.instrument.overnight_fee.is_some());
+    assert!(market.dealing_rules.min_guaranteed_stop_distance.is_some());
+}
+
+#[test]
+fn error_response() {
+    let error: APIError = fixture("error");
+    assert_eq!(error.error_code, "error.invalid.details");
+    assert!(error.errors.is_none());
+}